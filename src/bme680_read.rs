@@ -7,19 +7,27 @@
 use rust_general::led::Led;
 use rust_general::chip::Chip;
 use rust_general::bme680::BME680;
+use rust_general::retry::RetryPolicy;
+use rust_general::board::I2c1Recovery;
+use rust_general::compat::{Eh02Delay, Eh02I2c};
+use rust_general::shared::recoverable_critical_section_bus;
 
 use cortex_m_rt::entry;
 use panic_reset as _;
 use stm32h7xx_hal::{pac, prelude::*};
+use stm32h7xx_hal::delay::DelayFromCountDownTimer;
 use rtt_target::{rtt_init_log, rprintln};
-use log::{info, LevelFilter};
-
-use shared_bus;
+use log::{error, info, LevelFilter};
 
 // const GREEN: &str = "\x1b[32m";
 // const RED: &str = "\x1b[31m";
 // const RESET: &str = "\x1b[0m";
 
+// Log a failed access instead of letting panic-reset restart the MCU
+fn report<T, E: core::fmt::Display>(result: Result<T, E>) -> Option<T> {
+    result.map_err(|err| error!("{}", err)).ok()
+}
+
 #[entry]
 fn main() -> ! {
 
@@ -47,8 +55,7 @@ fn main() -> ! {
     let gpiob = dp.GPIOB.split(ccdr.peripheral.GPIOB);
     let scl = gpiob.pb8.into_alternate_open_drain();
     let sda = gpiob.pb9.into_alternate_open_drain();
-    let i2c = dp.I2C1.i2c((scl, sda), 50.kHz(), ccdr.peripheral.I2C1, &ccdr.clocks);
-    let i2c_manager = shared_bus::BusManagerSimple::new(i2c);  // This is the shared bus manager
+    let i2c = dp.I2C1.i2c_unchecked(50.kHz(), ccdr.peripheral.I2C1, &ccdr.clocks);  // Pins are kept for bus recovery
 
     // LED class
     let gpioe = dp.GPIOE.split(ccdr.peripheral.GPIOE);
@@ -56,11 +63,22 @@ fn main() -> ! {
     let mut delay = cp.SYST.delay(ccdr.clocks);
     let mut led = Led::new(led_pin);
 
+    // Separate timer for I2C retries, SysTick is owned by the LED delay
+    let retry_timer = dp.TIM2.timer(1.kHz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+    let retry_delay = Eh02Delay::new(DelayFromCountDownTimer::new(retry_timer));
+
+    // Bus recovery clocks SCL by hand, timed with its own timer
+    let recovery_timer = dp.TIM3.timer(1.MHz(), ccdr.peripheral.TIM3, &ccdr.clocks);
+    let recovery = I2c1Recovery::new(scl, sda, Eh02Delay::new(DelayFromCountDownTimer::new(recovery_timer)));
+    let i2c_bus = recoverable_critical_section_bus(Eh02I2c::new(i2c), recovery);  // Shared bus, recovery runs under the bus lock
+
     // Set up BME680
     // 🔹 Probe for the chip
     let bme_address = 0x76;
-    let bme_chip = Chip{i2c: i2c_manager.acquire_i2c(), i2c_addr: bme_address, _map: core::marker::PhantomData};
+    let bme_chip = Chip::new_recoverable(&i2c_bus, bme_address)
+        .with_retry(RetryPolicy::bus_errors(3, 1_000).with_recover_after(3), retry_delay);  // 3 attempts, 1ms apart, recover after 3 bus errors
     let mut bme = BME680::new(bme_chip).expect("failed to init bme");
+    bme.chip.enable_cache();
     bme.config(1).expect("Unable to configure BME680");
 
     // Start loop
@@ -76,39 +94,39 @@ fn main() -> ! {
         led.blink(&mut delay, 1000);
 
         // Read register with generic register read
-        bme.chip.read_field("chip_id").expect("Unable to read register");
-        bme.chip.read_reg(0xD0).expect("Unable to read register");
+        report(bme.chip.read_field("chip_id"));
+        report(bme.chip.read_reg(0xD0));
 
         rprintln!();
 
-        bme.chip.write_reg(0x74, 0b11100011).expect("Unable to read register");
-        bme.chip.read_reg(0x74).expect("Unable to read register");
-        bme.chip.read_field("osrs_t").expect("Unable to read register");
+        report(bme.chip.write_reg(0x74, 0b11100011));
+        report(bme.chip.read_reg(0x74));
+        report(bme.chip.read_field("osrs_t"));
 
         rprintln!();
 
-        bme.chip.write_reg(0x74, 0b00011100).expect("Unable to read register");
-        bme.chip.read_reg(0x74).expect("Unable to read register");
-        bme.chip.read_field("osrs_t").expect("Unable to read register");
+        report(bme.chip.write_reg(0x74, 0b00011100));
+        report(bme.chip.read_reg(0x74));
+        report(bme.chip.read_field("osrs_t"));
 
         rprintln!();
 
-        bme.chip.write_field("osrs_t", 0b101).expect("Unable to read register");
-        bme.chip.read_field("osrs_t").expect("Unable to read register");
+        report(bme.chip.write_field("osrs_t", 0b101));
+        report(bme.chip.read_field("osrs_t"));
 
         rprintln!();
 
-        bme.chip.write_reg_str("osrs_t", 0b101).expect("Unable to read register");
-        bme.chip.read_reg_str("osrs_t").expect("Unable to read register");
+        report(bme.chip.write_reg_str("osrs_t", 0b101));
+        report(bme.chip.read_reg_str("osrs_t"));
 
         rprintln!();
 
         let reg_vals = &mut [0u8; 4];
-        bme.chip.read_regs_str("Ctrl_hum", reg_vals).expect("Unable to read register");
+        report(bme.chip.read_regs_str("Ctrl_hum", reg_vals));
 
         rprintln!();
 
-        bme.read_temperature().expect("Unable to read temperature");
+        report(bme.read_temperature());
 
         rprintln!();
 
@@ -5,14 +5,19 @@
 
 use rust_general::led::Led;
 use rust_general::chip::Chip;
+use rust_general::compat::Eh02I2c;
+use rust_general::shared::critical_section_bus;
 
 use cortex_m_rt::entry;
 use panic_reset as _;
 use stm32h7xx_hal::{pac, prelude::*};
 use rtt_target::{rtt_init_log, rprintln};
-use log::{info, LevelFilter};
+use log::{error, info, LevelFilter};
 
-use shared_bus;
+// Log a failed access instead of letting panic-reset restart the MCU
+fn report<T, E: core::fmt::Display>(result: Result<T, E>) -> Option<T> {
+    result.map_err(|err| error!("{}", err)).ok()
+}
 
 #[entry]
 fn main() -> ! {
@@ -42,7 +47,7 @@ fn main() -> ! {
     let scl = gpiob.pb8.into_alternate_open_drain();
     let sda = gpiob.pb9.into_alternate_open_drain();
     let i2c = dp.I2C1.i2c((scl, sda), 50.kHz(), ccdr.peripheral.I2C1, &ccdr.clocks);
-    let i2c_manager = shared_bus::BusManagerSimple::new(i2c);  // This is the shared bus manager
+    let i2c_bus = critical_section_bus(Eh02I2c::new(i2c));  // Shared bus, usable from interrupts too
 
     // LED class
     let gpioe = dp.GPIOE.split(ccdr.peripheral.GPIOE);
@@ -52,7 +57,7 @@ fn main() -> ! {
 
     // Set up for generic chip
     let bme_address = 0x76;
-    let mut chip = Chip::new_generic(i2c_manager.acquire_i2c(), bme_address);
+    let mut chip: Chip<_> = Chip::new_critical_section(&i2c_bus, bme_address);
 
     // Start loop
     info!("Start Loop...");
@@ -67,22 +72,22 @@ fn main() -> ! {
         led.blink(&mut delay, 1000);
 
         // Read register with generic register read
-        let _field_val2 = chip.read_reg(0xD0).expect("Unable to read register");
+        let _field_val2 = report(chip.read_reg(0xD0));
 
         rprintln!();
 
-        chip.write_reg(0x74, 0b11100011).expect("Unable to read register");
-        chip.read_reg(0x74).expect("Unable to read register");
+        report(chip.write_reg(0x74, 0b11100011));
+        report(chip.read_reg(0x74));
 
         rprintln!();
 
-        chip.write_reg(0x74, 0b00011100).expect("Unable to read register");
-        chip.read_reg(0x74).expect("Unable to read register");
+        report(chip.write_reg(0x74, 0b00011100));
+        report(chip.read_reg(0x74));
 
         rprintln!();
 
         let reg_vals = &mut [0u8; 4];
-        chip.read_regs(0x74, reg_vals).expect("Unable to read register");
+        report(chip.read_regs(0x74, reg_vals));
 
         rprintln!();
 
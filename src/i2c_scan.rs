@@ -25,15 +25,16 @@
 // use mylib::i2c;
 // use mylib::i2c::I2c;
 use rust_general::led::Led;
+use rust_general::compat::{Eh02Delay, Eh02I2c};
+use rust_general::scan::{scan_identify, Probe};
 
 use cortex_m_rt::entry;
 use panic_reset as _;
 use stm32h7xx_hal::{pac, prelude::*};
 use rtt_target::{rtt_init_log, rprintln};
-use log::{info, LevelFilter};
+use log::{info, warn, LevelFilter};
 
 const GREEN: &str = "\x1b[32m";
-const RED: &str = "\x1b[31m";
 const RESET: &str = "\x1b[0m";
 
 #[entry]
@@ -63,33 +64,30 @@ fn main() -> ! {
     let gpiob = dp.GPIOB.split(ccdr.peripheral.GPIOB);
     let scl = gpiob.pb8.into_alternate_open_drain();
     let sda = gpiob.pb9.into_alternate_open_drain();
-    let mut i2c = dp.I2C1.i2c((scl, sda), 100.kHz(), ccdr.peripheral.I2C1, &ccdr.clocks);
-    let dummy_data = [0x00];
+    let i2c = dp.I2C1.i2c((scl, sda), 100.kHz(), ccdr.peripheral.I2C1, &ccdr.clocks);
+    let mut i2c = Eh02I2c::new(i2c);
 
     // LED class
     let gpioe = dp.GPIOE.split(ccdr.peripheral.GPIOE);
     let led_pin = gpioe.pe1.into_push_pull_output();
-    let mut delay = cp.SYST.delay(ccdr.clocks);
+    let mut delay = Eh02Delay::new(cp.SYST.delay(ccdr.clocks));
     let mut led = Led::new(led_pin);
 
     info!("Start Loop...");
     rprintln!();
 
     loop {
-        led.blink(&mut delay, 1000);
-
-        for addr in 0x03..=0x77 {
-
-            // Match is like "switch" in python
-            // Ok and Err are common across Rust as returns
-            match i2c.write(addr, &dummy_data) {
-                Ok(()) => info!("{}Found device at address 0x{:02X}{}", GREEN, addr, RESET),
-                Err(_) => info!("{}No device at address 0x{:02X}{}", RED, addr, RESET),
+        led.blink(&mut delay.delay, 1000);
+
+        // The HAL cannot issue a zero-length write, so probe with a one byte read
+        match scan_identify(&mut i2c, Probe::Read, &mut delay) {
+            Ok(found) => {
+                for result in found.iter() {
+                    info!("{}{}{}", GREEN, result, RESET);
+                }
+                info!("{} device(s) found", found.len());
             }
-
-            // Delay to make sure the I2C bus is not overwhelmed
-            // cortex_m::asm::delay(10_000);  // runs n CPU cycles. So if the clock is 100Mhz, this is 0.1ms
-            delay.delay_ms(10u16);
+            Err(err) => warn!("{}", err),
         }
         rprintln!();
     }
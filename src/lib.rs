@@ -1,14 +1,82 @@
 #![no_std]
 
 // Point modules into src/lib/...
+#[path = "lib/error.rs"]
+pub mod error;
+
+#[path = "lib/interface.rs"]
+pub mod interface;
+
+#[path = "lib/recovery.rs"]
+pub mod recovery;
+
 #[path = "lib/chip.rs"]
 pub mod chip;
 
+#[path = "lib/chip_async.rs"]
+pub mod chip_async;
+
 #[path = "lib/chip_map.rs"]
 pub mod chip_map;
 
+#[path = "lib/snapshot.rs"]
+pub mod snapshot;
+
+#[path = "lib/shadow.rs"]
+pub mod shadow;
+
+#[path = "lib/batch.rs"]
+pub mod batch;
+
+#[path = "lib/retry.rs"]
+pub mod retry;
+
+#[path = "lib/smbus.rs"]
+pub mod smbus;
+
+#[path = "lib/command.rs"]
+pub mod command;
+
 #[path = "lib/bme680.rs"]
 pub mod bme680;
 
+#[path = "lib/bme680_manager.rs"]
+pub mod bme680_manager;
+
+#[path = "lib/bme680_async.rs"]
+pub mod bme680_async;
+
+#[path = "lib/catalog.rs"]
+pub mod catalog;
+
+#[path = "lib/shared.rs"]
+pub mod shared;
+
+#[path = "lib/mux.rs"]
+pub mod mux;
+
+#[path = "lib/scan.rs"]
+pub mod scan;
+
+#[path = "lib/monitor.rs"]
+pub mod monitor;
+
+#[path = "lib/scheduler.rs"]
+pub mod scheduler;
+
+#[path = "lib/sample.rs"]
+pub mod sample;
+
+#[path = "lib/telemetry.rs"]
+pub mod telemetry;
+
 #[path = "lib/led.rs"]
-pub mod led;
\ No newline at end of file
+pub mod led;
+
+#[cfg(feature = "eh02")]
+#[path = "lib/compat.rs"]
+pub mod compat;
+
+#[cfg(feature = "eh02")]
+#[path = "lib/board.rs"]
+pub mod board;
@@ -0,0 +1,238 @@
+// batch.rs
+use embedded_hal::delay::DelayNs;
+use heapless::Vec;
+use log::{self, info};
+
+use crate::chip::{Chip, I2CError};
+use crate::chip_map::{Field, FieldMapProvider};
+use crate::error::field_name;
+use crate::interface::RegisterInterface;
+
+// Number of field changes a single batch can hold
+pub const MAX_BATCH_FIELDS: usize = 16;
+
+// Number of fields a single read_fields call can return
+pub const MAX_READ_FIELDS: usize = 16;
+
+// A queued field change
+struct PendingField {
+    name: &'static str,
+    field: &'static Field,
+    value: u8,
+}
+
+// Field changes collected by Chip::modify and applied together on commit
+pub struct FieldBatch<'a, I2C: RegisterInterface, MAP, DELAY> {
+    chip: &'a mut Chip<I2C, MAP, DELAY>,
+    pending: Vec<PendingField, MAX_BATCH_FIELDS>,
+    error: Option<I2CError<I2C>>,
+}
+
+impl<I2C, MAP, DELAY> FieldBatch<'_, I2C, MAP, DELAY>
+where
+    I2C: RegisterInterface,
+    MAP: FieldMapProvider,
+    DELAY: DelayNs,
+{
+    pub fn set(mut self, field: &str, field_val: u8) -> Self {
+        // Queue a field change, errors are held back and reported by commit
+        if self.error.is_some() {
+            return self;
+        }
+
+        let Some((name, field_dets)) = MAP::map().get_entry(field) else {
+            self.error = Some(I2CError::field_not_found(field));
+            return self;
+        };
+
+        if !field_dets.writable {
+            self.error = Some(I2CError::FieldReadOnly { field: field_name(field) });
+            return self;
+        }
+
+        if self.pending.push(PendingField { name, field: field_dets, value: field_val }).is_err() {
+            self.error = Some(I2CError::BatchFull { field: field_name(field) });
+        }
+
+        self
+    }
+
+    pub fn commit(self) -> Result<(), I2CError<I2C>> {
+        // Apply the queued changes with one read and one write per register
+        // Registers are written in the order of the last field set in each of them,
+        // so a field set last (e.g. mode) always lands in the last write
+        let FieldBatch { chip, pending, error } = self;
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let mut regs: Vec<(u16, usize), MAX_BATCH_FIELDS> = Vec::new();
+        for (idx, pending_field) in pending.iter().enumerate() {
+            match regs.iter_mut().find(|(reg, _)| *reg == pending_field.field.reg) {
+                Some(entry) => entry.1 = idx,
+                None => { let _ = regs.push((pending_field.field.reg, idx)); }
+            }
+        }
+        regs.sort_unstable_by_key(|(_, last_idx)| *last_idx);
+
+        for (reg, _) in regs.iter() {
+            // Use the shadow copy of the register if there is one, otherwise read it
+            let mut reg_val = match chip.cached_reg(*reg) {
+                Some(reg_val) => reg_val,
+                None => chip.read_reg(*reg)?,
+            };
+
+            for pending_field in pending.iter().filter(|pending_field| pending_field.field.reg == *reg) {
+                reg_val = pending_field.field.insert(reg_val, pending_field.value);
+                info!("Write Field: {}, {:0width$b}, 0x{:.02X}, {}", pending_field.name, pending_field.value, pending_field.value, pending_field.value, width=pending_field.field.bits as usize);
+            }
+
+            let old_level = log::max_level();
+            log::set_max_level(log::LevelFilter::Off);
+            let result = chip.write_reg(*reg, reg_val);
+            log::set_max_level(old_level);
+            result?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<I2C, MAP, DELAY> Chip<I2C, MAP, DELAY>
+where
+    I2C: RegisterInterface,
+    MAP: FieldMapProvider,
+    DELAY: DelayNs,
+{
+    pub fn modify(&mut self) -> FieldBatch<'_, I2C, MAP, DELAY> {
+        // Start a batch of field changes, applied with commit()
+        FieldBatch { chip: self, pending: Vec::new(), error: None }
+    }
+
+    pub fn read_fields(&mut self, fields: &[&str]) -> Result<Vec<u8, MAX_READ_FIELDS>, I2CError<I2C>> {
+        // Read several fields, returned in the order given
+        // Their registers are read once each, contiguous registers in a single burst
+        let mut field_dets: Vec<&'static Field, MAX_READ_FIELDS> = Vec::new();
+        let mut regs: Vec<u16, MAX_READ_FIELDS> = Vec::new();
+        for field in fields.iter() {
+            let dets = MAP::get_field(field).ok_or_else(|| I2CError::field_not_found(field))?;
+            field_dets.push(dets).map_err(|_| I2CError::BatchFull { field: field_name(field) })?;
+            if !regs.contains(&dets.reg) {
+                let _ = regs.push(dets.reg);
+            }
+        }
+        regs.sort_unstable();
+
+        let mut reg_vals: Vec<(u16, u8), MAX_READ_FIELDS> = Vec::new();
+        let old_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+        let result = self.read_spans(&regs, |reg, value| { let _ = reg_vals.push((reg, value)); });
+        log::set_max_level(old_level);
+        result?;
+
+        let mut field_vals = Vec::new();
+        for (field, dets) in fields.iter().zip(field_dets.iter()) {
+            let reg_val = reg_vals.iter().find(|(reg, _)| *reg == dets.reg).map_or(0, |(_, value)| *value);
+            let field_val = dets.extract(reg_val);
+            info!("Read Field: {}, {:0width$b}, 0x{:.02X}, {}", field, field_val, field_val, field_val, width=dets.bits as usize);
+            let _ = field_vals.push(field_val);
+        }
+
+        Ok(field_vals)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, Operation};
+    use phf::Map;
+    use phf_macros::phf_map;
+
+    struct TestMap;
+
+    static TEST_MAP: Map<&'static str, Field> = phf_map! {
+        "osrs_h" => Field { reg: 0x72, offset: 0, bits: 3, writable: true, volatile: false },
+        "osrs_t" => Field { reg: 0x74, offset: 5, bits: 3, writable: true, volatile: false },
+        "mode" => Field { reg: 0x74, offset: 0, bits: 2, writable: true, volatile: false },
+        "filter" => Field { reg: 0x75, offset: 2, bits: 3, writable: true, volatile: false },
+        "measuring" => Field { reg: 0x1d, offset: 5, bits: 1, writable: false, volatile: true },
+    };
+
+    impl FieldMapProvider for TestMap {
+        fn map() -> &'static Map<&'static str, Field> {
+            &TEST_MAP
+        }
+    }
+
+    // 8-bit register file, records every register write in order
+    struct RegFile {
+        regs: [u8; 256],
+        writes: Vec<(u8, u8), 16>,
+    }
+
+    impl ErrorType for RegFile {
+        type Error = ErrorKind;
+    }
+
+    impl I2c for RegFile {
+        fn transaction(&mut self, _address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+            let mut reg = None;
+            for operation in operations.iter_mut() {
+                match operation {
+                    Operation::Write(bytes) => {
+                        for byte in bytes.iter() {
+                            match reg {
+                                None => reg = Some(*byte),
+                                Some(addr) => {
+                                    self.regs[addr as usize] = *byte;
+                                    let _ = self.writes.push((addr, *byte));
+                                    reg = Some(addr.wrapping_add(1));
+                                }
+                            }
+                        }
+                    }
+                    Operation::Read(bytes) => {
+                        for byte in bytes.iter_mut() {
+                            let addr = reg.unwrap_or(0);
+                            *byte = self.regs[addr as usize];
+                            reg = Some(addr.wrapping_add(1));
+                        }
+                    }
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn chip() -> Chip<RegFile, TestMap> {
+        Chip::new(RegFile { regs: [0; 256], writes: Vec::new() }, 0x76)
+    }
+
+    #[test]
+    fn commit_writes_each_register_once_in_order_of_its_last_field() {
+        let mut chip = chip();
+        chip.modify().set("mode", 0b01).set("osrs_h", 0b101).set("filter", 0b010).set("osrs_t", 0b101).commit().unwrap();
+
+        // 0x74 holds the last field set, so it is written last even though mode came first
+        assert_eq!(chip.i2c.writes.as_slice(), &[(0x72, 0b101), (0x75, 0b010 << 2), (0x74, 0b1010_0001)]);
+    }
+
+    #[test]
+    fn commit_keeps_bits_outside_the_batch() {
+        let mut chip = chip();
+        chip.i2c.regs[0x75] = 0b1110_0011;
+        chip.modify().set("filter", 0b101).commit().unwrap();
+
+        assert_eq!(chip.i2c.writes.as_slice(), &[(0x75, 0b1111_0111)]);
+    }
+
+    #[test]
+    fn commit_writes_nothing_after_a_rejected_field() {
+        let mut chip = chip();
+        let result = chip.modify().set("mode", 0b01).set("measuring", 1).set("osrs_t", 0b101).commit();
+
+        assert!(matches!(result, Err(I2CError::FieldReadOnly { .. })));
+        assert!(chip.i2c.writes.is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-use embedded_hal::blocking::i2c;
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::ErrorType;
+use embedded_hal::spi::SpiDevice;
 
 use log::{self, info};
 
@@ -7,29 +9,31 @@ use core::fmt::Write;
 
 use crate::chip::Chip;
 use crate::chip::I2CError;
+use crate::interface::{spi_reg, ReadSegments, RegisterInterface, SpiInterface};
 use crate::chip_map::{Field, FieldMapProvider};
+use crate::monitor::BusEvent;
+use crate::retry::NoDelay;
+use crate::bme680_manager::SensorId;
+use crate::sample::{Channel, Sample, SampleSink, Status, Unit};
 
 // Efficient map for register maps
 use phf::Map;
 use phf_macros::phf_map;
 
-pub struct BME680<I2C> {
-    pub chip: Chip<I2C, Bme680FieldMap>,
-    pub cal_codes: CalCodes,
-    pub temp_comp: i32,
-    pub t_fine: i32,
+pub struct BME680<I2C, DELAY=NoDelay> {
+    pub chip: Chip<I2C, Bme680FieldMap, DELAY>,
+    pub comp: Compensation,
 }
 
-impl<I2C> BME680<I2C>
+impl<I2C, DELAY> BME680<I2C, DELAY>
 where
-    I2C: i2c::WriteRead,
+    I2C: RegisterInterface,
+    DELAY: DelayNs,
 {
-    pub fn new(chip: Chip<I2C, Bme680FieldMap>) -> Result<Self, I2CError<I2C>> {
+    pub fn new(chip: Chip<I2C, Bme680FieldMap, DELAY>) -> Result<Self, I2CError<I2C>> {
         let mut this = Self {
             chip,
-            cal_codes: CalCodes::default(), // ← created here
-            temp_comp: 0,
-            t_fine: 0,
+            comp: Compensation::default(),
         };
 
         this.read_cal_codes()?;
@@ -39,96 +43,44 @@ where
 
     pub fn config(&mut self, profile_num: u8) -> Result<(), I2CError<I2C>> {
 
-        // Other Sensor Settings
-        self.chip.write_field("osrs_h", 0b101)?;  // 16x oversampling
-        self.chip.write_field("osrs_t", 0b101)?;  // 16x oversampling
-        self.chip.write_field("osrs_p", 0b101)?;  // 16x oversampling
-        self.chip.write_field("filter", 0b010)?;  // Filter coefficient of 3 - form of averaging filter
-
-        // Gas Sensor Settings
-        self.chip.write_field("gas_range_r", 4)?; // Set Gas Range
-        self.chip.write_field("run_gas", 0b1)?; // Turn on Gas Sensor
-        self.chip.write_field("nb_conv", profile_num)?; // Set Heater profile to profile 0
+        // Sensor and gas settings, in one batch
+        let mut batch = self.chip.modify();
+        for (field, field_val) in config_fields(profile_num) {
+            batch = batch.set(field, field_val);
+        }
+        batch.commit()?;
 
         // Set time between beginning of the heat phase and start of resistance conversion
-        self.set_gas_wait(0b00011110, profile_num)?; // This should be 30ms
+        self.set_gas_wait(GAS_WAIT_30MS, profile_num)?;
 
         // Set heater temperature
-        self.set_heater_temp(300, profile_num)?;  // Set heater profile 0 to 300C
+        self.set_heater_temp(HEATER_TEMP_C, profile_num)?;
 
         Ok(())
     }
 
     pub fn set_gas_wait(&mut self, wait_time_ms: u8, profile_num: u8) -> Result<(), I2CError<I2C>> {
-        let mut buf: String<16> = String::new();
-        write!(buf, "gas_wait_{}", profile_num).unwrap();   
-        self.chip.write_field(&buf, wait_time_ms)
+        self.chip.write_field(&profile_field("gas_wait", profile_num), wait_time_ms)
     }
 
     pub fn set_heater_temp(&mut self, target_temp: i16, profile_num: u8) -> Result<(), I2CError<I2C>> {
 
-        // --- Get calibration values ---
-        let par_g1 = self.cal_codes.par_g1;
-        let par_g2 = self.cal_codes.par_g2;
-        let par_g3 = self.cal_codes.par_g3;
-
         // --- Ensure temperature compensation is available ---
-        if self.temp_comp == 0 {self.read_temperature()?;}
-        let amb_temp = (self.temp_comp / 100) as i32;
-
-        // --- Read intermediates ---
-        let res_heat_range = self.chip.read_field("res_heat_range")? as i32;
-        let res_heat_val = self.chip.read_field("res_heat_val")? as i32;
+        if self.comp.needs_temperature() {self.read_temperature()?;}
 
         // --- Calculate heater resistance ---
-        let var1 = (((amb_temp * par_g3 as i32) / 10) << 8) as i32;
-        let var2 = (par_g1 as i32 + 784)* (((((par_g2 as i32 + 154_009) * target_temp as i32 * 5) / 100) + 3_276_800) / 10);
-        let var3 = var1 + (var2 >> 1);
-        let var4 = var3 / (res_heat_range + 4);
-        let var5 = 131 * res_heat_val + 65_536;
-        let res_heat_x100 = ((var4 / var5) - 250) * 34;
-        let res_heat_x = ((res_heat_x100 + 50) / 100) as u8;
+        let res_heat_x = self.comp.heater_resistance(target_temp);
 
-        // Format field name and write
-        let mut buf: String<16> = String::new();
-        write!(buf, "res_heat_{}", profile_num).unwrap();   
-        self.chip.write_field(&buf, res_heat_x)
+        self.chip.write_field(&profile_field("res_heat", profile_num), res_heat_x)
     }
 
     pub fn read_cal_codes(&mut self) -> Result<(), I2CError<I2C>> {
-        let rf = |name: &str, this: &mut Self| this.chip.read_field(name);
-        let rr = |reg: u8,  this: &mut Self| this.chip.read_reg(reg);
-
-        // Temperature
-        self.cal_codes.par_t1 =(rf("par_t1", self)? as u16) | ((rr(0xea, self)? as u16) << 8);
-        self.cal_codes.par_t2 =(rf("par_t2", self)? as i16) | ((rr(0x8b, self)? as i16) << 8);
-        self.cal_codes.par_t3 = rf("par_t3", self)? as i16;
-
-        // Pressure
-        self.cal_codes.par_p1 =(rf("par_p1", self)? as u16) | ((rr(0x8f, self)? as u16) << 8);
-        self.cal_codes.par_p2 =(rf("par_p2", self)? as i16) | ((rr(0x91, self)? as i16) << 8);
-        self.cal_codes.par_p3 = rf("par_p3", self)? as i8;
-        self.cal_codes.par_p4 =(rf("par_p4", self)? as i16) | ((rr(0x95, self)? as i16) << 8);
-        self.cal_codes.par_p5 =(rf("par_p5", self)? as i16) | ((rr(0x97, self)? as i16) << 8);
-        self.cal_codes.par_p6 = rf("par_p6", self)? as i8;
-        self.cal_codes.par_p7 = rf("par_p7", self)? as i8;
-        self.cal_codes.par_p8 =(rf("par_p8", self)? as i16) | ((rr(0x9d, self)? as i16) << 8);
-        self.cal_codes.par_p9 =(rf("par_p9", self)? as i16) | ((rr(0x9f, self)? as i16) << 8);
-        self.cal_codes.par_p10 = rf("par_p10", self)?;
-
-        // Humidity
-        self.cal_codes.par_h1 =((rf("par_h1", self)? & 0x0F) as u16) | ((rr(0xe3, self)? as u16) << 4);
-        self.cal_codes.par_h2 =((rf("par_h2", self)? as u16) << 4) | ((rr(0xe2, self)? as u16) >> 4);
-        self.cal_codes.par_h3 = rf("par_h3", self)? as i8;
-        self.cal_codes.par_h4 = rf("par_h4", self)? as i8;
-        self.cal_codes.par_h5 = rf("par_h5", self)? as i8;
-        self.cal_codes.par_h6 = rf("par_h6", self)?;
-        self.cal_codes.par_h7 = rf("par_h7", self)? as i8;
-
-        // Gas
-        self.cal_codes.par_g1 = rf("par_g1", self)? as i8;
-        self.cal_codes.par_g2 =(rf("par_g2", self)? as i16) | ((rr(0xec, self)? as i16) << 8);
-        self.cal_codes.par_g3 = rf("par_g3", self)? as i8;
+        // Burst read each calibration block, then parse
+        let mut cal_regs = CalRegs::default();
+        for (block, (start, len)) in cal_regs.blocks.iter_mut().zip(CAL_BLOCKS) {
+            self.chip.read_regs(start, &mut block[..len])?;
+        }
+        self.comp.cal_codes = CalCodes::from_regs(&cal_regs);
 
         Ok(())
     }
@@ -136,142 +88,248 @@ where
     pub fn read_temperature(&mut self) -> Result<i32, I2CError<I2C>> {
         let old_level = log::max_level();
         log::set_max_level(log::LevelFilter::Off);
+        let temp_regs = self.read_temp_regs();
+        log::set_max_level(old_level);
+
+        Ok(self.comp.temperature(&temp_regs?))
+    }
 
+    fn read_temp_regs(&mut self) -> Result<[u8; TEMP_LEN], I2CError<I2C>> {
+        // Trigger a forced measurement and read back the raw temperature
         self.chip.write_field("mode", 0b01)?;
 
-        let mut temp_out = [0u8; 3];
-        self.chip.read_regs_str("temp_msb", &mut temp_out)?;
+        let mut temp_regs = [0u8; TEMP_LEN];
+        self.chip.read_regs_str("temp_msb", &mut temp_regs)?;
+        Ok(temp_regs)
+    }
+
+    pub fn reinit(&mut self) -> Result<(), I2CError<I2C>> {
+        // Device was power cycled or swapped, forget everything known about it and reload calibration
+        // Settings made with config have to be applied again
+        self.chip.invalidate();
+        self.comp.reset();
+        self.read_cal_codes()
+    }
 
-        // 20-bit ADC value
-        let temp_adc: u32 =
-            ((temp_out[0] as u32) << 12) |
-            ((temp_out[1] as u32) << 4)  |
-            ((temp_out[2] as u32) >> 4);
+    pub fn handle_event(&mut self, event: &BusEvent) -> Result<bool, I2CError<I2C>> {
+        // Re-initialize when a bus monitor sees this device reappear, returns true if it did
+        match event {
+            BusEvent::DeviceAdded(addr, _) if *addr == self.chip.i2c_addr => {
+                self.reinit()?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
 
-        let temp_comp = self.calibrate_temperature(temp_adc);
-        log::set_max_level(old_level);
+    pub fn measure<D: DelayNs>(&mut self, delay: &mut D) -> Result<Measurement, I2CError<I2C>> {
+        // Forced measurement: trigger, wait out the conversion and heater phase, read back
+        let duration_ms = self.start_measurement()?;
+        delay.delay_ms(duration_ms);
+        self.read_measurement()
+    }
+
+    pub fn start_measurement(&mut self) -> Result<u32, I2CError<I2C>> {
+        // Trigger a forced measurement, returns how long it takes in ms
+        let mut ctrl_regs = [0u8; CTRL_LEN];
+        self.chip.read_regs(CTRL_START, &mut ctrl_regs)?;
+        let gas_wait = self.chip.read_reg(gas_wait_reg(&ctrl_regs))?;
 
-        // Log statement with decimal points
-        let whole = temp_comp / 100;
-        let frac  = temp_comp % 100;
-        info!("Temperature: {}.{:02} °C", whole, frac);
+        self.chip.write_field("mode", 0b01)?;
 
-        Ok(temp_comp)
+        Ok(measurement_duration_ms(&ctrl_regs, gas_wait))
     }
 
-    pub fn calibrate_temperature(&mut self, temp_adc: u32) -> i32 {
-        // Calibration constants
-        let par_t1 = self.cal_codes.par_t1; // i16
-        let par_t2 = self.cal_codes.par_t2; // i16
-        let par_t3 = self.cal_codes.par_t3; // u16
+    pub fn sample<D: DelayNs, S: SampleSink>(&mut self, delay: &mut D, sensor: SensorId, timestamp_us: u64, sink: &mut S) -> Result<Measurement, I2CError<I2C>> {
+        // Measure and hand the result to a sink, a failed read still produces error records
+        let result = self.measure(delay);
+        match &result {
+            Ok(measurement) => sink.write_all(&measurement.samples(sensor, timestamp_us)),
+            Err(_) => sink.write_all(&Measurement::error_samples(sensor, timestamp_us)),
+        }
+        result
+    }
 
-        // Promote to i64 for intermediate math
-        let var1 = ((temp_adc as i32 >> 3) - ((par_t1 as i32) << 1)) as i64;
-        let var2 = ((var1 * par_t2 as i64) >> 11) as i64;
-        let var3 = ((((var1 >> 1) * (var1 >> 1)) >> 12) * ((par_t3 as i64) << 4)) >> 14;
+    pub fn read_measurement(&mut self) -> Result<Measurement, I2CError<I2C>> {
+        // Read and compensate the result of the last forced measurement
+        let mut data_regs = [0u8; DATA_LEN];
+        self.chip.read_regs(DATA_START, &mut data_regs)?;
 
-        let t_fine = (var2 + var3) as i32;
-        let temp_comp = ((t_fine * 5 + 128) >> 8) as i32;
+        Ok(self.comp.measurement(&data_regs))
+    }
+}
 
-        // Save intermediate values
-        self.t_fine = t_fine;
-        self.temp_comp = temp_comp;
+// SPI transport for the BME680
+// Register space is split into two 128 byte pages selected by spi_mem_page,
+// page 0 holds 0x80..=0xff and page 1 holds 0x00..=0x7f, the status register is on both
+pub struct Bme680Spi<SPI> {
+    pub spi: SpiInterface<SPI>,
+    page: Option<u8>,  // None until known, e.g. after power-up or a soft reset
+}
 
-        temp_comp
+impl<SPI> Bme680Spi<SPI>
+where
+    SPI: SpiDevice,
+{
+    pub fn new(spi: SPI) -> Self {
+        Self { spi: SpiInterface::new(spi), page: None }
+    }
+
+    pub fn release(self) -> SPI {
+        self.spi.release()
+    }
+
+    fn select_page(&mut self, reg: u8) -> Result<(), <Self as ErrorType>::Error> {
+        // Switch pages only when the register lives on the other one
+        let page = if reg >= 0x80 { 0 } else { 1 };
+        if reg == STATUS_REG || self.page == Some(page) {
+            return Ok(());
+        }
+
+        let page_field = Bme680FieldMap::get_field("spi_mem_page").expect("field missing from BME680 map");
+        let mut status = [0u8];
+        self.spi.read_regs(0, &[STATUS_REG], &mut status)?;
+        self.spi.write_regs(0, &[STATUS_REG], &[page_field.insert(status[0], page)])?;
+        self.page = Some(page);
+
+        Ok(())
     }
 }
 
+impl<SPI> ErrorType for Bme680Spi<SPI>
+where
+    SPI: SpiDevice,
+{
+    type Error = <SpiInterface<SPI> as ErrorType>::Error;
+}
+
+impl<SPI> RegisterInterface for Bme680Spi<SPI>
+where
+    SPI: SpiDevice,
+{
+    fn read_regs_into(&mut self, addr: u8, reg: &[u8], segments: &mut ReadSegments<'_>) -> Result<(), Self::Error> {
+        // Bursts are expected to stay within one page
+        self.select_page(spi_reg(reg)?)?;
+        self.spi.read_regs_into(addr, reg, segments)
+    }
+
+    fn write_regs(&mut self, addr: u8, reg: &[u8], reg_values: &[u8]) -> Result<(), Self::Error> {
+        let reg_addr = spi_reg(reg)?;
+        self.select_page(reg_addr)?;
+        self.spi.write_regs(addr, reg, reg_values)?;
+
+        // Soft reset puts the device back on page 0, writing status may change the page
+        let touches = |target: u8| (target.wrapping_sub(reg_addr) as usize) < reg_values.len();
+        if touches(RESET_REG) || touches(STATUS_REG) {
+            self.page = None;
+        }
+
+        Ok(())
+    }
+}
+
+// Registers the SPI transport needs to know about
+const STATUS_REG: u8 = 0x73;
+const RESET_REG: u8 = 0xe0;
+
 #[derive(Copy, Clone)]
 pub struct Bme680FieldMap;
 
 impl FieldMapProvider for Bme680FieldMap {
-    fn get_field(name: &str) -> Option<&'static Field> {
-        FIELD_MAP.get(name)
+    fn map() -> &'static Map<&'static str, Field> {
+        &FIELD_MAP
     }
 }
 
 pub static FIELD_MAP: Map<&'static str, Field> = phf_map! {
-    "status" => Field { reg: 0x73, offset: 0, bits: 8, writable: true },
-    "reset" => Field { reg: 0xe0, offset: 0, bits: 8, writable: true },
-    "Id" => Field { reg: 0xd0, offset: 0, bits: 8, writable: false },
-    "chip_id" => Field { reg: 0xd0, offset: 0, bits: 8, writable: false },
-    "Config" => Field { reg: 0x75, offset: 0, bits: 8, writable: true },
-    "filter" => Field { reg: 0x75, offset: 2, bits: 3, writable: true },
-    "ctrl_meas" => Field { reg: 0x74, offset: 0, bits: 8, writable: true },
-    "osrs_t" => Field { reg: 0x74, offset: 5, bits: 3, writable: true },
-    "osrs_p" => Field { reg: 0x74, offset: 2, bits: 3, writable: true },
-    "mode" => Field { reg: 0x74, offset: 0, bits: 2, writable: true },
-
-    "Ctrl_hum" => Field { reg: 0x72, offset: 0, bits: 8, writable: true },
-    "osrs_h" => Field { reg: 0x72, offset: 0, bits: 3, writable: true },
-
-    "ctrl_gas_1" => Field { reg: 0x71, offset: 0, bits: 8, writable: true },
-    "ctrl_gas_0" => Field { reg: 0x70, offset: 4, bits: 2, writable: true },
-    "run_gas" => Field { reg: 0x71, offset: 4, bits: 1, writable: true },
-    "nb_conv" => Field { reg: 0x71, offset: 0, bits: 4, writable: true },
-    "heat_off" => Field { reg: 0x70, offset: 3, bits: 1, writable: true },
-    "gas_wait_9" => Field { reg: 0x6d, offset: 0, bits: 8, writable: true },
-    "gas_wait_8" => Field { reg: 0x6c, offset: 0, bits: 8, writable: true },
-    "gas_wait_7" => Field { reg: 0x6b, offset: 0, bits: 8, writable: true },
-    "gas_wait_6" => Field { reg: 0x6a, offset: 0, bits: 8, writable: true },
-    "gas_wait_5" => Field { reg: 0x69, offset: 0, bits: 8, writable: true },
-    "gas_wait_4" => Field { reg: 0x68, offset: 0, bits: 8, writable: true },
-    "gas_wait_3" => Field { reg: 0x67, offset: 0, bits: 8, writable: true },
-    "gas_wait_2" => Field { reg: 0x66, offset: 0, bits: 8, writable: true },
-    "gas_wait_1" => Field { reg: 0x65, offset: 0, bits: 8, writable: true },
-    "gas_wait_0" => Field { reg: 0x64, offset: 0, bits: 8, writable: true },
-    "res_heat_9" => Field { reg: 0x63, offset: 0, bits: 8, writable: true },
-    "res_heat_8" => Field { reg: 0x62, offset: 0, bits: 8, writable: true },
-    "res_heat_7" => Field { reg: 0x61, offset: 0, bits: 8, writable: true },
-    "res_heat_6" => Field { reg: 0x60, offset: 0, bits: 8, writable: true },
-    "res_heat_5" => Field { reg: 0x5f, offset: 0, bits: 8, writable: true },
-    "res_heat_4" => Field { reg: 0x5e, offset: 0, bits: 8, writable: true },
-    "res_heat_3" => Field { reg: 0x5d, offset: 0, bits: 8, writable: true },
-    "res_heat_2" => Field { reg: 0x5c, offset: 0, bits: 8, writable: true },
-    "res_heat_1" => Field { reg: 0x5b, offset: 0, bits: 8, writable: true },
-    "res_heat_0" => Field { reg: 0x5a, offset: 0, bits: 8, writable: true },
-
-    "gas_r_lsb" => Field { reg: 0x2b, offset: 0, bits: 8, writable: false },
-    "gas_range_r" => Field { reg: 0x2b, offset: 0, bits: 4, writable: false },
-    "heat_stab_r" => Field { reg: 0x2b, offset: 4, bits: 1, writable: false },
-    "gas_valid_r" => Field { reg: 0x2b, offset: 5, bits: 1, writable: false },
-
-    "gas_r_msb" => Field { reg: 0x2a, offset: 0, bits: 8, writable: false },
-    "hum_lsb" => Field { reg: 0x26, offset: 0, bits: 8, writable: false },
-    "hum_msb" => Field { reg: 0x25, offset: 0, bits: 8, writable: false },
-    "temp_xlsb" => Field { reg: 0x24, offset: 4, bits: 4, writable: false },
-    "temp_lsb" => Field { reg: 0x23, offset: 0, bits: 8, writable: false },
-    "temp_msb" => Field { reg: 0x22, offset: 0, bits: 8, writable: false },
-    "press_xlsb" => Field { reg: 0x21, offset: 4, bits: 4, writable: false },
-    "press_lsb" => Field { reg: 0x20, offset: 0, bits: 8, writable: false },
-    "press_msb" => Field { reg: 0x1f, offset: 0, bits: 8, writable: false },
-
-    "par_t1" => Field { reg: 0xe9, offset: 0, bits: 8, writable: false },
-    "par_t2" => Field { reg: 0x8a, offset: 0, bits: 8, writable: false },
-    "par_t3" => Field { reg: 0x8c, offset: 0, bits: 8, writable: false },
-    "par_p1" => Field { reg: 0x8e, offset: 0, bits: 8, writable: false },
-    "par_p2" => Field { reg: 0x90, offset: 0, bits: 8, writable: false },
-    "par_p3" => Field { reg: 0x92, offset: 0, bits: 8, writable: false },
-    "par_p4" => Field { reg: 0x94, offset: 0, bits: 8, writable: false },
-    "par_p5" => Field { reg: 0x96, offset: 0, bits: 8, writable: false },
-    "par_p6" => Field { reg: 0x99, offset: 0, bits: 8, writable: false },
-    "par_p7" => Field { reg: 0x98, offset: 0, bits: 8, writable: false },
-    "par_p8" => Field { reg: 0x9c, offset: 0, bits: 8, writable: false },
-    "par_p9" => Field { reg: 0x9e, offset: 0, bits: 8, writable: false },
-    "par_p10" => Field { reg: 0xa0, offset: 0, bits: 8, writable: false },
-    "par_h1" => Field { reg: 0xe2, offset: 0, bits: 8, writable: false },
-    "par_h2" => Field { reg: 0xe1, offset: 0, bits: 8, writable: false },
-    "par_h3" => Field { reg: 0xe4, offset: 0, bits: 8, writable: false },
-    "par_h4" => Field { reg: 0xe5, offset: 0, bits: 8, writable: false },
-    "par_h5" => Field { reg: 0xe6, offset: 0, bits: 8, writable: false },
-    "par_h6" => Field { reg: 0xe7, offset: 0, bits: 8, writable: false },
-    "par_h7" => Field { reg: 0xe8, offset: 0, bits: 8, writable: false },
-    "par_g1" => Field { reg: 0xed, offset: 0, bits: 8, writable: false },
-    "par_g2" => Field { reg: 0xeb, offset: 0, bits: 8, writable: false },
-    "par_g3" => Field { reg: 0xee, offset: 0, bits: 8, writable: false },
-    "res_heat_range" => Field { reg: 0x02, offset: 4, bits: 2, writable: false },
-    "res_heat_val" => Field { reg: 0x00, offset: 0, bits: 8, writable: false },
-    "range_switching_error" => Field { reg: 0x04, offset: 0, bits: 8, writable: false },
+    "status" => Field { reg: 0x73, offset: 0, bits: 8, writable: true, volatile: false },
+    "spi_mem_page" => Field { reg: 0x73, offset: 4, bits: 1, writable: true, volatile: false },
+    "reset" => Field { reg: 0xe0, offset: 0, bits: 8, writable: true, volatile: true },
+    "Id" => Field { reg: 0xd0, offset: 0, bits: 8, writable: false, volatile: false },
+    "chip_id" => Field { reg: 0xd0, offset: 0, bits: 8, writable: false, volatile: false },
+    "Config" => Field { reg: 0x75, offset: 0, bits: 8, writable: true, volatile: false },
+    "filter" => Field { reg: 0x75, offset: 2, bits: 3, writable: true, volatile: false },
+    "ctrl_meas" => Field { reg: 0x74, offset: 0, bits: 8, writable: true, volatile: false },
+    "osrs_t" => Field { reg: 0x74, offset: 5, bits: 3, writable: true, volatile: false },
+    "osrs_p" => Field { reg: 0x74, offset: 2, bits: 3, writable: true, volatile: false },
+    "mode" => Field { reg: 0x74, offset: 0, bits: 2, writable: true, volatile: true },
+
+    "Ctrl_hum" => Field { reg: 0x72, offset: 0, bits: 8, writable: true, volatile: false },
+    "osrs_h" => Field { reg: 0x72, offset: 0, bits: 3, writable: true, volatile: false },
+
+    "ctrl_gas_1" => Field { reg: 0x71, offset: 0, bits: 8, writable: true, volatile: false },
+    "ctrl_gas_0" => Field { reg: 0x70, offset: 4, bits: 2, writable: true, volatile: false },
+    "run_gas" => Field { reg: 0x71, offset: 4, bits: 1, writable: true, volatile: false },
+    "nb_conv" => Field { reg: 0x71, offset: 0, bits: 4, writable: true, volatile: false },
+    "heat_off" => Field { reg: 0x70, offset: 3, bits: 1, writable: true, volatile: false },
+    "gas_wait_9" => Field { reg: 0x6d, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_8" => Field { reg: 0x6c, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_7" => Field { reg: 0x6b, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_6" => Field { reg: 0x6a, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_5" => Field { reg: 0x69, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_4" => Field { reg: 0x68, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_3" => Field { reg: 0x67, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_2" => Field { reg: 0x66, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_1" => Field { reg: 0x65, offset: 0, bits: 8, writable: true, volatile: false },
+    "gas_wait_0" => Field { reg: 0x64, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_9" => Field { reg: 0x63, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_8" => Field { reg: 0x62, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_7" => Field { reg: 0x61, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_6" => Field { reg: 0x60, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_5" => Field { reg: 0x5f, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_4" => Field { reg: 0x5e, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_3" => Field { reg: 0x5d, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_2" => Field { reg: 0x5c, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_1" => Field { reg: 0x5b, offset: 0, bits: 8, writable: true, volatile: false },
+    "res_heat_0" => Field { reg: 0x5a, offset: 0, bits: 8, writable: true, volatile: false },
+
+    "meas_status_0" => Field { reg: 0x1d, offset: 0, bits: 8, writable: false, volatile: true },
+    "new_data_0" => Field { reg: 0x1d, offset: 7, bits: 1, writable: false, volatile: true },
+    "gas_measuring" => Field { reg: 0x1d, offset: 6, bits: 1, writable: false, volatile: true },
+    "measuring" => Field { reg: 0x1d, offset: 5, bits: 1, writable: false, volatile: true },
+    "gas_meas_index_0" => Field { reg: 0x1d, offset: 0, bits: 4, writable: false, volatile: true },
+
+    "gas_r_lsb" => Field { reg: 0x2b, offset: 0, bits: 8, writable: false, volatile: true },
+    "gas_range_r" => Field { reg: 0x2b, offset: 0, bits: 4, writable: false, volatile: true },
+    "heat_stab_r" => Field { reg: 0x2b, offset: 4, bits: 1, writable: false, volatile: true },
+    "gas_valid_r" => Field { reg: 0x2b, offset: 5, bits: 1, writable: false, volatile: true },
+
+    "gas_r_msb" => Field { reg: 0x2a, offset: 0, bits: 8, writable: false, volatile: true },
+    "hum_lsb" => Field { reg: 0x26, offset: 0, bits: 8, writable: false, volatile: true },
+    "hum_msb" => Field { reg: 0x25, offset: 0, bits: 8, writable: false, volatile: true },
+    "temp_xlsb" => Field { reg: 0x24, offset: 4, bits: 4, writable: false, volatile: true },
+    "temp_lsb" => Field { reg: 0x23, offset: 0, bits: 8, writable: false, volatile: true },
+    "temp_msb" => Field { reg: 0x22, offset: 0, bits: 8, writable: false, volatile: true },
+    "press_xlsb" => Field { reg: 0x21, offset: 4, bits: 4, writable: false, volatile: true },
+    "press_lsb" => Field { reg: 0x20, offset: 0, bits: 8, writable: false, volatile: true },
+    "press_msb" => Field { reg: 0x1f, offset: 0, bits: 8, writable: false, volatile: true },
+
+    "par_t1" => Field { reg: 0xe9, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_t2" => Field { reg: 0x8a, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_t3" => Field { reg: 0x8c, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p1" => Field { reg: 0x8e, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p2" => Field { reg: 0x90, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p3" => Field { reg: 0x92, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p4" => Field { reg: 0x94, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p5" => Field { reg: 0x96, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p6" => Field { reg: 0x99, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p7" => Field { reg: 0x98, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p8" => Field { reg: 0x9c, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p9" => Field { reg: 0x9e, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_p10" => Field { reg: 0xa0, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_h1" => Field { reg: 0xe2, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_h2" => Field { reg: 0xe1, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_h3" => Field { reg: 0xe4, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_h4" => Field { reg: 0xe5, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_h5" => Field { reg: 0xe6, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_h6" => Field { reg: 0xe7, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_h7" => Field { reg: 0xe8, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_g1" => Field { reg: 0xed, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_g2" => Field { reg: 0xeb, offset: 0, bits: 8, writable: false, volatile: false },
+    "par_g3" => Field { reg: 0xee, offset: 0, bits: 8, writable: false, volatile: false },
+    "res_heat_range" => Field { reg: 0x02, offset: 4, bits: 2, writable: false, volatile: false },
+    "res_heat_val" => Field { reg: 0x00, offset: 0, bits: 8, writable: false, volatile: false },
+    "range_switching_error" => Field { reg: 0x04, offset: 0, bits: 8, writable: false, volatile: false },
 
 };
 
@@ -314,4 +372,440 @@ pub struct CalCodes {
     pub gas_adc: i16,
     pub gas_range: i8,
     pub range_switching_error: i8,
-}
\ No newline at end of file
+}
+
+// Calibration registers, read as three bursts
+pub const CAL_BLOCKS: [(u16, usize); 3] = [(0x8a, 23), (0xe1, 14), (0x00, 5)];
+const CAL_BLOCK_MAX: usize = 23;
+
+// Gas, humidity, measurement and IIR filter control registers 0x70..=0x74
+pub const CTRL_START: u16 = 0x70;
+pub const CTRL_LEN: usize = 5;
+
+// Status and measurement result registers 0x1d..=0x2b
+pub const DATA_START: u16 = 0x1d;
+pub const DATA_LEN: usize = 15;
+
+// Temperature result registers 0x22..=0x24
+pub const TEMP_LEN: usize = 3;
+
+// Defaults used by config
+pub const GAS_WAIT_30MS: u8 = 0b00011110;
+pub const HEATER_TEMP_C: i16 = 300;
+
+pub fn config_fields(profile_num: u8) -> [(&'static str, u8); 6] {
+    // Field settings applied by config, in write order
+    // ctrl_hum must be written before ctrl_meas for the humidity setting to take effect
+    [
+        ("osrs_h", 0b101),  // 16x oversampling
+        ("osrs_t", 0b101),  // 16x oversampling
+        ("osrs_p", 0b101),  // 16x oversampling
+        ("filter", 0b010),  // Filter coefficient of 3 - form of averaging filter
+        ("run_gas", 0b1),  // Turn on Gas Sensor
+        ("nb_conv", profile_num),  // Select heater profile
+    ]
+}
+
+pub fn profile_field(prefix: &str, profile_num: u8) -> String<16> {
+    // Per heater profile field name, e.g. gas_wait_3
+    let mut buf: String<16> = String::new();
+    write!(buf, "{}_{}", prefix, profile_num).unwrap();
+    buf
+}
+
+fn block_field(name: &str, start: u16, block: &[u8]) -> u8 {
+    // Field value out of a burst read that starts at register `start`
+    let field = Bme680FieldMap::get_field(name).expect("field missing from BME680 map");
+    field.extract(block[(field.reg - start) as usize])
+}
+
+pub fn gas_wait_reg(ctrl_regs: &[u8; CTRL_LEN]) -> u16 {
+    // gas_wait register of the heater profile selected by nb_conv
+    let nb_conv = block_field("nb_conv", CTRL_START, ctrl_regs);
+    Bme680FieldMap::get_field("gas_wait_0").map_or(0x64, |field| field.reg) + nb_conv as u16
+}
+
+pub fn measurement_duration_ms(ctrl_regs: &[u8; CTRL_LEN], gas_wait: u8) -> u32 {
+    // TPH conversion time from the oversampling settings, plus the heater phase
+    // Follows the Bosch reference driver
+    const OS_TO_CYCLES: [u32; 8] = [0, 1, 2, 4, 8, 16, 16, 16];
+    let cycles = OS_TO_CYCLES[block_field("osrs_t", CTRL_START, ctrl_regs) as usize]
+        + OS_TO_CYCLES[block_field("osrs_p", CTRL_START, ctrl_regs) as usize]
+        + OS_TO_CYCLES[block_field("osrs_h", CTRL_START, ctrl_regs) as usize];
+
+    let mut tph_dur_us = cycles * 1963;
+    tph_dur_us += 477 * 4;  // TPH switching duration
+    tph_dur_us += 477 * 5;  // Gas measurement duration
+    let mut duration_ms = (tph_dur_us + 500) / 1000 + 1;  // Round up, plus wake up time
+
+    if block_field("run_gas", CTRL_START, ctrl_regs) == 1 {
+        duration_ms += gas_wait_ms(gas_wait);
+    }
+
+    duration_ms
+}
+
+pub fn gas_wait_ms(gas_wait: u8) -> u32 {
+    // 6-bit value with a x1, x4, x16 or x64 multiplier in the top two bits
+    const MULTIPLIERS: [u32; 4] = [1, 4, 16, 64];
+    (gas_wait & 0x3f) as u32 * MULTIPLIERS[(gas_wait >> 6) as usize]
+}
+
+// Raw copy of the calibration blocks
+pub struct CalRegs {
+    pub blocks: [[u8; CAL_BLOCK_MAX]; 3],
+}
+
+impl CalRegs {
+    pub fn reg(&self, reg: u16) -> u8 {
+        // Value of a calibration register by address
+        for (block, (start, len)) in self.blocks.iter().zip(CAL_BLOCKS) {
+            if reg >= start && ((reg - start) as usize) < len {
+                return block[(reg - start) as usize];
+            }
+        }
+        0
+    }
+
+    pub fn field(&self, name: &str) -> u8 {
+        let field = Bme680FieldMap::get_field(name).expect("field missing from BME680 map");
+        field.extract(self.reg(field.reg))
+    }
+}
+
+impl Default for CalRegs {
+    fn default() -> Self {
+        Self { blocks: [[0; CAL_BLOCK_MAX]; 3] }
+    }
+}
+
+// Uncompensated ADC values from one measurement
+pub struct RawData {
+    pub new_data: bool,
+    pub temp_adc: u32,
+    pub press_adc: u32,
+    pub hum_adc: u16,
+    pub gas_adc: u16,
+    pub gas_range: u8,
+    pub gas_valid: bool,
+    pub heat_stab: bool,
+}
+
+impl RawData {
+    pub fn from_regs(data_regs: &[u8; DATA_LEN]) -> Self {
+        let r = |reg: u16| data_regs[(reg - DATA_START) as usize] as u32;
+        let f = |name: &str| block_field(name, DATA_START, data_regs);
+
+        Self {
+            new_data: f("new_data_0") == 1,
+            press_adc: (r(0x1f) << 12) | (r(0x20) << 4) | (r(0x21) >> 4),  // 20-bit
+            temp_adc: (r(0x22) << 12) | (r(0x23) << 4) | (r(0x24) >> 4),  // 20-bit
+            hum_adc: ((r(0x25) << 8) | r(0x26)) as u16,  // 16-bit
+            gas_adc: ((r(0x2a) << 2) | (r(0x2b) >> 6)) as u16,  // 10-bit
+            gas_range: f("gas_range_r"),
+            gas_valid: f("gas_valid_r") == 1,
+            heat_stab: f("heat_stab_r") == 1,
+        }
+    }
+}
+
+// Calibration plus the last temperature reading, the one compensation path for both drivers
+#[derive(Default)]
+pub struct Compensation {
+    pub cal_codes: CalCodes,
+    pub temp_comp: i32,  // 0.01 °C, 0 until the first reading
+    pub t_fine: i32,
+}
+
+impl Compensation {
+    pub fn needs_temperature(&self) -> bool {
+        // Heater settings depend on the ambient temperature, read one first if none is known
+        self.temp_comp == 0
+    }
+
+    pub fn temperature(&mut self, temp_regs: &[u8; TEMP_LEN]) -> i32 {
+        // Compensate temp_msb..=temp_xlsb and keep the result for the heater and other channels
+        let temp_adc = ((temp_regs[0] as u32) << 12) | ((temp_regs[1] as u32) << 4) | ((temp_regs[2] as u32) >> 4);
+        let (temp_comp, t_fine) = self.cal_codes.compensate_temperature(temp_adc);
+        self.temp_comp = temp_comp;
+        self.t_fine = t_fine;
+
+        let sign = if temp_comp < 0 { "-" } else { "" };
+        let whole = temp_comp.unsigned_abs() / 100;
+        let frac  = temp_comp.unsigned_abs() % 100;
+        info!("Temperature: {}{}.{:02} °C", sign, whole, frac);
+
+        temp_comp
+    }
+
+    pub fn heater_resistance(&self, target_temp: i16) -> u8 {
+        // res_heat_x for a target temperature at the last known ambient temperature
+        self.cal_codes.heater_resistance(target_temp, self.temp_comp / 100)
+    }
+
+    pub fn measurement(&mut self, data_regs: &[u8; DATA_LEN]) -> Measurement {
+        // Compensate a block of result registers and keep its temperature
+        let raw = RawData::from_regs(data_regs);
+        if !raw.new_data {
+            info!("BME680: no new data");
+        }
+
+        let measurement = self.cal_codes.compensate(&raw);
+        self.t_fine = measurement.t_fine;
+        self.temp_comp = measurement.temperature;
+        measurement.log();
+
+        measurement
+    }
+
+    pub fn reset(&mut self) {
+        // Forget the last reading, e.g. after the device was power cycled
+        self.temp_comp = 0;
+        self.t_fine = 0;
+    }
+}
+
+pub const SAMPLES_PER_MEASUREMENT: usize = 4;  // Records produced per measurement
+
+// Compensated result of one measurement
+#[derive(Copy, Clone, Debug)]
+pub struct Measurement {
+    pub temperature: i32,  // 0.01 °C
+    pub pressure: u32,  // Pa
+    pub humidity: u32,  // 0.001 %RH
+    pub gas_resistance: Option<u32>,  // Ohm, None if the heater was not stable
+    pub t_fine: i32,
+}
+
+impl Measurement {
+    pub fn log(&self) {
+        let sign = if self.temperature < 0 { "-" } else { "" };
+        let temperature = self.temperature.unsigned_abs();
+        info!("Temperature: {}{}.{:02} °C", sign, temperature / 100, temperature % 100);
+        info!("Pressure: {}.{:02} hPa", self.pressure / 100, self.pressure % 100);
+        info!("Humidity: {}.{:03} %", self.humidity / 1000, self.humidity % 1000);
+        if let Some(gas_resistance) = self.gas_resistance {
+            info!("Gas Resistance: {} Ohm", gas_resistance);
+        }
+    }
+
+    pub fn samples(&self, sensor: SensorId, timestamp_us: u64) -> [Sample; SAMPLES_PER_MEASUREMENT] {
+        let sample = |channel, value, unit, status| Sample { sensor, timestamp_us, channel, value, unit, status };
+        let (gas_resistance, gas_status) = match self.gas_resistance {
+            Some(gas_resistance) => (gas_resistance as i32, Status::Ok),
+            None => (0, Status::Unstable),
+        };
+        [
+            sample(Channel::Temperature, self.temperature, Unit::CentiDegC, Status::Ok),
+            sample(Channel::Pressure, self.pressure as i32, Unit::Pascal, Status::Ok),
+            sample(Channel::Humidity, self.humidity as i32, Unit::MilliPercentRh, Status::Ok),
+            sample(Channel::GasResistance, gas_resistance, Unit::Ohm, gas_status),
+        ]
+    }
+
+    pub fn error_samples(sensor: SensorId, timestamp_us: u64) -> [Sample; SAMPLES_PER_MEASUREMENT] {
+        [
+            Sample::error(sensor, timestamp_us, Channel::Temperature, Unit::CentiDegC),
+            Sample::error(sensor, timestamp_us, Channel::Pressure, Unit::Pascal),
+            Sample::error(sensor, timestamp_us, Channel::Humidity, Unit::MilliPercentRh),
+            Sample::error(sensor, timestamp_us, Channel::GasResistance, Unit::Ohm),
+        ]
+    }
+}
+
+impl CalCodes {
+    pub fn from_regs(cal_regs: &CalRegs) -> Self {
+        let rf = |name: &str| cal_regs.field(name);
+        let rr = |reg: u16| cal_regs.reg(reg);
+
+        Self {
+            // Temperature
+            par_t1: (rf("par_t1") as u16) | ((rr(0xea) as u16) << 8),
+            par_t2: (rf("par_t2") as i16) | ((rr(0x8b) as i16) << 8),
+            par_t3: rf("par_t3") as i8 as i16,
+
+            // Pressure
+            par_p1: (rf("par_p1") as u16) | ((rr(0x8f) as u16) << 8),
+            par_p2: (rf("par_p2") as i16) | ((rr(0x91) as i16) << 8),
+            par_p3: rf("par_p3") as i8,
+            par_p4: (rf("par_p4") as i16) | ((rr(0x95) as i16) << 8),
+            par_p5: (rf("par_p5") as i16) | ((rr(0x97) as i16) << 8),
+            par_p6: rf("par_p6") as i8,
+            par_p7: rf("par_p7") as i8,
+            par_p8: (rf("par_p8") as i16) | ((rr(0x9d) as i16) << 8),
+            par_p9: (rf("par_p9") as i16) | ((rr(0x9f) as i16) << 8),
+            par_p10: rf("par_p10"),
+
+            // Humidity
+            par_h1: ((rf("par_h1") & 0x0F) as u16) | ((rr(0xe3) as u16) << 4),
+            par_h2: ((rf("par_h2") as u16) << 4) | ((rr(0xe2) as u16) >> 4),
+            par_h3: rf("par_h3") as i8,
+            par_h4: rf("par_h4") as i8,
+            par_h5: rf("par_h5") as i8,
+            par_h6: rf("par_h6"),
+            par_h7: rf("par_h7") as i8,
+
+            // Gas
+            par_g1: rf("par_g1") as i8,
+            par_g2: (rf("par_g2") as i16) | ((rr(0xec) as i16) << 8),
+            par_g3: rf("par_g3") as i8,
+
+            // Misc
+            res_heat_range: rf("res_heat_range") as i8,
+            res_heat_val: rf("res_heat_val") as i8,
+            range_switching_error: (rf("range_switching_error") as i8 & 0xf0u8 as i8) / 16,
+            ..Self::default()
+        }
+    }
+
+    pub fn compensate(&self, raw: &RawData) -> Measurement {
+        let (temperature, t_fine) = self.compensate_temperature(raw.temp_adc);
+        let gas_resistance = if raw.gas_valid && raw.heat_stab {
+            Some(self.compensate_gas(raw.gas_adc, raw.gas_range))
+        } else {
+            None
+        };
+
+        Measurement {
+            temperature,
+            pressure: self.compensate_pressure(raw.press_adc, t_fine),
+            humidity: self.compensate_humidity(raw.hum_adc, t_fine),
+            gas_resistance,
+            t_fine,
+        }
+    }
+
+    pub fn compensate_temperature(&self, temp_adc: u32) -> (i32, i32) {
+        // Returns the temperature in 0.01 °C along with t_fine
+        let par_t1 = self.par_t1;
+        let par_t2 = self.par_t2;
+        let par_t3 = self.par_t3;
+
+        // Promote to i64 for intermediate math
+        let var1 = ((temp_adc as i32 >> 3) - ((par_t1 as i32) << 1)) as i64;
+        let var2 = (var1 * par_t2 as i64) >> 11;
+        let var3 = ((((var1 >> 1) * (var1 >> 1)) >> 12) * ((par_t3 as i64) << 4)) >> 14;
+
+        let t_fine = (var2 + var3) as i32;
+        let temp_comp = (t_fine * 5 + 128) >> 8;
+
+        (temp_comp, t_fine)
+    }
+
+    pub fn compensate_pressure(&self, press_adc: u32, t_fine: i32) -> u32 {
+        // Pressure in Pa, integer formula from the Bosch reference driver
+        let mut var1 = ((t_fine as i64) >> 1) - 64_000;
+        let mut var2 = ((((var1 >> 2) * (var1 >> 2)) >> 11) * self.par_p6 as i64) >> 2;
+        var2 += (var1 * self.par_p5 as i64) << 1;
+        var2 = (var2 >> 2) + ((self.par_p4 as i64) << 16);
+        var1 = (((((var1 >> 2) * (var1 >> 2)) >> 13) * ((self.par_p3 as i64) << 5)) >> 3)
+            + ((self.par_p2 as i64 * var1) >> 1);
+        var1 >>= 18;
+        var1 = ((32_768 + var1) * self.par_p1 as i64) >> 15;
+        if var1 == 0 {
+            return 0;
+        }
+
+        let mut press_comp = 1_048_576 - press_adc as i64;
+        press_comp = (press_comp - (var2 >> 12)) * 3125;
+        press_comp = if press_comp >= (1 << 30) {
+            (press_comp / var1) << 1
+        } else {
+            (press_comp << 1) / var1
+        };
+
+        let var1 = (self.par_p9 as i64 * (((press_comp >> 3) * (press_comp >> 3)) >> 13)) >> 12;
+        let var2 = ((press_comp >> 2) * self.par_p8 as i64) >> 13;
+        let var3 = ((press_comp >> 8) * (press_comp >> 8) * (press_comp >> 8) * self.par_p10 as i64) >> 17;
+        press_comp += (var1 + var2 + var3 + ((self.par_p7 as i64) << 7)) >> 4;
+
+        press_comp.max(0) as u32
+    }
+
+    pub fn compensate_humidity(&self, hum_adc: u16, t_fine: i32) -> u32 {
+        // Relative humidity in 0.001 %, integer formula from the Bosch reference driver
+        let temp_scaled = ((t_fine as i64 * 5) + 128) >> 8;
+        let var1 = (hum_adc as i64 - (self.par_h1 as i64 * 16))
+            - (((temp_scaled * self.par_h3 as i64) / 100) >> 1);
+        let var2 = (self.par_h2 as i64
+            * (((temp_scaled * self.par_h4 as i64) / 100)
+                + (((temp_scaled * ((temp_scaled * self.par_h5 as i64) / 100)) >> 6) / 100)
+                + (1 << 14)))
+            >> 10;
+        let var3 = var1 * var2;
+        let var4 = (((self.par_h6 as i64) << 7) + ((temp_scaled * self.par_h7 as i64) / 100)) >> 4;
+        let var5 = ((var3 >> 14) * (var3 >> 14)) >> 10;
+        let var6 = (var4 * var5) >> 1;
+        let hum_comp = (((var3 + var6) >> 10) * 1000) >> 12;
+
+        hum_comp.clamp(0, 100_000) as u32
+    }
+
+    pub fn compensate_gas(&self, gas_adc: u16, gas_range: u8) -> u32 {
+        // Gas resistance in Ohm, integer formula and lookup tables from the Bosch reference driver
+        const LOOKUP_1: [i64; 16] = [
+            2147483647, 2147483647, 2147483647, 2147483647, 2147483647, 2126008810, 2147483647, 2130303777,
+            2147483647, 2147483647, 2143188679, 2136746228, 2147483647, 2126008810, 2147483647, 2147483647,
+        ];
+        const LOOKUP_2: [i64; 16] = [
+            4096000000, 2048000000, 1024000000, 512000000, 255744255, 127110228, 64000000, 32258064,
+            16016016, 8000000, 4000000, 2000000, 1000000, 500000, 250000, 125000,
+        ];
+
+        let gas_range = (gas_range & 0x0f) as usize;
+        let var1 = ((1340 + 5 * self.range_switching_error as i64) * LOOKUP_1[gas_range]) >> 16;
+        let var2 = ((gas_adc as i64) << 15) - 16_777_216 + var1;
+        let var3 = (LOOKUP_2[gas_range] * var1) >> 9;
+        if var2 == 0 {
+            return 0;
+        }
+
+        ((var3 + (var2 >> 1)) / var2) as u32
+    }
+
+    pub fn heater_resistance(&self, target_temp: i16, amb_temp: i32) -> u8 {
+        // res_heat_x register value for a heater target temperature in °C
+
+        // --- Get calibration values ---
+        let par_g1 = self.par_g1;
+        let par_g2 = self.par_g2;
+        let par_g3 = self.par_g3;
+        let res_heat_range = self.res_heat_range as i32;
+        let res_heat_val = self.res_heat_val as i32;
+
+        // --- Calculate heater resistance ---
+        let var1 = ((amb_temp * par_g3 as i32) / 10) << 8;
+        let var2 = (par_g1 as i32 + 784)* (((((par_g2 as i32 + 154_009) * target_temp as i32 * 5) / 100) + 3_276_800) / 10);
+        let var3 = var1 + (var2 >> 1);
+        let var4 = var3 / (res_heat_range + 4);
+        let var5 = 131 * res_heat_val + 65_536;
+        let res_heat_x100 = ((var4 / var5) - 250) * 34;
+        ((res_heat_x100 + 50) / 100) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctrl_regs(osrs: u8, run_gas: bool) -> [u8; CTRL_LEN] {
+        // ctrl_gas_0, ctrl_gas_1, ctrl_hum, unused, ctrl_meas with the same oversampling everywhere
+        [0, (run_gas as u8) << 4, osrs, 0, (osrs << 5) | (osrs << 2)]
+    }
+
+    #[test]
+    fn duration_without_oversampling_or_gas() {
+        assert_eq!(measurement_duration_ms(&ctrl_regs(0b000, false), GAS_WAIT_30MS), 5);
+    }
+
+    #[test]
+    fn duration_at_16x_oversampling() {
+        assert_eq!(measurement_duration_ms(&ctrl_regs(0b101, false), GAS_WAIT_30MS), 100);
+    }
+
+    #[test]
+    fn duration_includes_the_heater_phase() {
+        assert_eq!(measurement_duration_ms(&ctrl_regs(0b101, true), GAS_WAIT_30MS), 130);
+        assert_eq!(measurement_duration_ms(&ctrl_regs(0b101, true), 0b01_011001), 200);  // 25 ms x4
+    }
+}
@@ -0,0 +1,120 @@
+// bme680_async.rs
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::chip_async::{AsyncChip, I2CError};
+use crate::monitor::BusEvent;
+use crate::bme680::{
+    config_fields, gas_wait_reg, measurement_duration_ms, profile_field, Bme680FieldMap, CalCodes, CalRegs,
+    Compensation, Measurement, CAL_BLOCKS, CTRL_LEN, CTRL_START, DATA_LEN, DATA_START, GAS_WAIT_30MS, HEATER_TEMP_C,
+    TEMP_LEN,
+};
+
+// Async BME680 driver, register map and compensation are shared with the blocking driver
+pub struct BME680Async<I2C> {
+    pub chip: AsyncChip<I2C, Bme680FieldMap>,
+    pub comp: Compensation,
+}
+
+impl<I2C> BME680Async<I2C>
+where
+    I2C: I2c,
+{
+    pub async fn new(chip: AsyncChip<I2C, Bme680FieldMap>) -> Result<Self, I2CError<I2C>> {
+        let mut this = Self {
+            chip,
+            comp: Compensation::default(),
+        };
+
+        this.read_cal_codes().await?;
+
+        Ok(this)
+    }
+
+    pub async fn config(&mut self, profile_num: u8) -> Result<(), I2CError<I2C>> {
+        // Same settings as the blocking driver, one field at a time
+        for (field, field_val) in config_fields(profile_num) {
+            self.chip.write_field(field, field_val).await?;
+        }
+
+        self.set_gas_wait(GAS_WAIT_30MS, profile_num).await?;
+        self.set_heater_temp(HEATER_TEMP_C, profile_num).await?;
+
+        Ok(())
+    }
+
+    pub async fn set_gas_wait(&mut self, wait_time_ms: u8, profile_num: u8) -> Result<(), I2CError<I2C>> {
+        self.chip.write_field(&profile_field("gas_wait", profile_num), wait_time_ms).await
+    }
+
+    pub async fn set_heater_temp(&mut self, target_temp: i16, profile_num: u8) -> Result<(), I2CError<I2C>> {
+        if self.comp.needs_temperature() {self.read_temperature().await?;}
+        let res_heat_x = self.comp.heater_resistance(target_temp);
+
+        self.chip.write_field(&profile_field("res_heat", profile_num), res_heat_x).await
+    }
+
+    pub async fn read_cal_codes(&mut self) -> Result<(), I2CError<I2C>> {
+        let mut cal_regs = CalRegs::default();
+        for (block, (start, len)) in cal_regs.blocks.iter_mut().zip(CAL_BLOCKS) {
+            self.chip.read_regs(start, &mut block[..len]).await?;
+        }
+        self.comp.cal_codes = CalCodes::from_regs(&cal_regs);
+
+        Ok(())
+    }
+
+    pub async fn read_temperature(&mut self) -> Result<i32, I2CError<I2C>> {
+        // Trigger a forced measurement and compensate the raw temperature
+        self.chip.write_field("mode", 0b01).await?;
+
+        let mut temp_regs = [0u8; TEMP_LEN];
+        self.chip.read_regs_str("temp_msb", &mut temp_regs).await?;
+
+        Ok(self.comp.temperature(&temp_regs))
+    }
+
+    pub async fn reinit(&mut self) -> Result<(), I2CError<I2C>> {
+        // Device was power cycled or swapped, reload calibration
+        // Settings made with config have to be applied again
+        self.comp.reset();
+        self.read_cal_codes().await
+    }
+
+    pub async fn handle_event(&mut self, event: &BusEvent) -> Result<bool, I2CError<I2C>> {
+        // Re-initialize when a bus monitor sees this device reappear, returns true if it did
+        match event {
+            BusEvent::DeviceAdded(addr, _) if *addr == self.chip.i2c_addr => {
+                self.reinit().await?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    pub async fn measure<D: DelayNs>(&mut self, delay: &mut D) -> Result<Measurement, I2CError<I2C>> {
+        // Forced measurement, the conversion and heater phase are awaited instead of blocking
+        let duration_ms = self.start_measurement().await?;
+        delay.delay_ms(duration_ms).await;
+        self.read_measurement().await
+    }
+
+    pub async fn start_measurement(&mut self) -> Result<u32, I2CError<I2C>> {
+        // Trigger a forced measurement, returns how long it takes in ms
+        let mut ctrl_regs = [0u8; CTRL_LEN];
+        self.chip.read_regs(CTRL_START, &mut ctrl_regs).await?;
+        let gas_wait = self.chip.read_reg(gas_wait_reg(&ctrl_regs)).await?;
+
+        self.chip.write_field("mode", 0b01).await?;
+
+        Ok(measurement_duration_ms(&ctrl_regs, gas_wait))
+    }
+
+    pub async fn read_measurement(&mut self) -> Result<Measurement, I2CError<I2C>> {
+        // Read and compensate the result of the last forced measurement
+        let mut data_regs = [0u8; DATA_LEN];
+        self.chip.bus_read(DATA_START, &mut data_regs).await?;
+
+        Ok(self.comp.measurement(&data_regs))
+    }
+}
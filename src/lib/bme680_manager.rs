@@ -0,0 +1,128 @@
+// bme680_manager.rs
+// Several BME680s measured together, one shared wait instead of one per sensor
+use embedded_hal::delay::DelayNs;
+use heapless::Vec;
+use log::warn;
+
+use crate::bme680::{Measurement, BME680, SAMPLES_PER_MEASUREMENT};
+use crate::chip::I2CError;
+use crate::interface::RegisterInterface;
+use crate::retry::NoDelay;
+use crate::sample::{Sample, SampleSink};
+
+// Caller assigned ID, e.g. bus or mux channel in the high nibble and 0x76/0x77 in the low bit
+pub type SensorId = u8;
+
+pub struct SensorReading<I2C: RegisterInterface> {
+    pub id: SensorId,
+    pub result: Result<Measurement, I2CError<I2C>>,
+}
+
+impl<I2C: RegisterInterface> SensorReading<I2C> {
+    pub fn samples(&self, timestamp_us: u64) -> [Sample; SAMPLES_PER_MEASUREMENT] {
+        match &self.result {
+            Ok(measurement) => measurement.samples(self.id, timestamp_us),
+            Err(_) => Measurement::error_samples(self.id, timestamp_us),
+        }
+    }
+}
+
+// Sensors must share a bus type, e.g. channels of one mux or devices of one shared bus
+pub struct Bme680Manager<I2C, const N: usize, DELAY=NoDelay> {
+    sensors: Vec<(SensorId, BME680<I2C, DELAY>), N>,
+}
+
+impl<I2C, const N: usize, DELAY> Bme680Manager<I2C, N, DELAY>
+where
+    I2C: RegisterInterface,
+    DELAY: DelayNs,
+{
+    pub fn new() -> Self {
+        Self { sensors: Vec::new() }
+    }
+
+    pub fn add(&mut self, id: SensorId, sensor: BME680<I2C, DELAY>) -> Option<BME680<I2C, DELAY>> {
+        // Hands the sensor back if the manager is full or the ID is already taken
+        if self.sensors.iter().any(|(sensor_id, _)| *sensor_id == id) {
+            return Some(sensor);
+        }
+        self.sensors.push((id, sensor)).err().map(|(_, sensor)| sensor)
+    }
+
+    pub fn remove(&mut self, id: SensorId) -> Option<BME680<I2C, DELAY>> {
+        let idx = self.sensors.iter().position(|(sensor_id, _)| *sensor_id == id)?;
+        Some(self.sensors.swap_remove(idx).1)
+    }
+
+    pub fn get_mut(&mut self, id: SensorId) -> Option<&mut BME680<I2C, DELAY>> {
+        self.sensors.iter_mut().find(|(sensor_id, _)| *sensor_id == id).map(|(_, sensor)| sensor)
+    }
+
+    pub fn sensors_mut(&mut self) -> impl Iterator<Item = (SensorId, &mut BME680<I2C, DELAY>)> {
+        self.sensors.iter_mut().map(|(id, sensor)| (*id, sensor))
+    }
+
+    pub fn len(&self) -> usize {
+        self.sensors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sensors.is_empty()
+    }
+
+    pub fn measure_all<D: DelayNs>(&mut self, delay: &mut D) -> Vec<SensorReading<I2C>, N> {
+        // Trigger every sensor, wait once for the slowest, then read them all
+        // A sensor that fails only fails its own reading
+        let mut started: Vec<Option<I2CError<I2C>>, N> = Vec::new();
+        let mut longest_ms = 0;
+        for (id, sensor) in self.sensors.iter_mut() {
+            let error = match sensor.start_measurement() {
+                Ok(duration_ms) => {
+                    longest_ms = longest_ms.max(duration_ms);
+                    None
+                }
+                Err(err) => {
+                    warn!("Sensor {} failed to start: {}", id, err);
+                    Some(err)
+                }
+            };
+            // Capacity matches the number of sensors
+            let _ = started.push(error);
+        }
+
+        delay.delay_ms(longest_ms);
+
+        let mut readings = Vec::new();
+        for ((id, sensor), start_error) in self.sensors.iter_mut().zip(started) {
+            let result = match start_error {
+                Some(err) => Err(err),
+                None => sensor.read_measurement(),
+            };
+            if let Err(err) = &result {
+                warn!("Sensor {} failed: {}", id, err);
+            }
+            let _ = readings.push(SensorReading { id: *id, result });
+        }
+
+        readings
+    }
+
+    pub fn sample_all<D: DelayNs, S: SampleSink>(&mut self, delay: &mut D, timestamp_us: u64, sink: &mut S) -> Vec<SensorReading<I2C>, N> {
+        // measure_all, with every reading also written to the sink
+        let readings = self.measure_all(delay);
+        for reading in readings.iter() {
+            sink.write_all(&reading.samples(timestamp_us));
+        }
+        readings
+    }
+}
+
+impl<I2C, const N: usize, DELAY> Default for Bme680Manager<I2C, N, DELAY>
+where
+    I2C: RegisterInterface,
+    DELAY: DelayNs,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,60 @@
+// board.rs
+// STM32H7 specific glue: I2C error mapping for stm32h7xx-hal and bus recovery for I2C1
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{ErrorKind, NoAcknowledgeSource};
+use stm32h7xx_hal::gpio::{self, Alternate, OpenDrain};
+use stm32h7xx_hal::i2c as hal_i2c;
+use stm32h7xx_hal::pac;
+
+use crate::compat::{ClassifyError, Eh02Pin};
+use crate::recovery::{clock_out_bus, BusRecovery};
+
+impl ClassifyError for hal_i2c::Error {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            hal_i2c::Error::Bus => ErrorKind::Bus,
+            hal_i2c::Error::Arbitration => ErrorKind::ArbitrationLoss,
+            hal_i2c::Error::NotAcknowledge => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown),
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+// Bus recovery for I2C1 on PB8 (SCL) and PB9 (SDA)
+// Create the bus with i2c_unchecked so the pins stay available here
+pub struct I2c1Recovery<D> {
+    pub scl: gpio::PB8<Alternate<4, OpenDrain>>,
+    pub sda: gpio::PB9<Alternate<4, OpenDrain>>,
+    pub delay: D,
+}
+
+impl<D> I2c1Recovery<D> {
+    pub fn new(scl: gpio::PB8<Alternate<4, OpenDrain>>, sda: gpio::PB9<Alternate<4, OpenDrain>>, delay: D) -> Self {
+        Self { scl, sda, delay }
+    }
+}
+
+impl<D> BusRecovery for I2c1Recovery<D>
+where
+    D: DelayNs,
+{
+    fn recover(&mut self) -> bool {
+        // Pins are GPIO only for the duration of the clocking, then back to the I2C alternate function
+        let Self { scl, sda, delay } = self;
+        let released = scl.with_open_drain_output(|scl| {
+            sda.with_open_drain_output(|sda| clock_out_bus(&mut Eh02Pin::new(scl), &mut Eh02Pin::new(sda), delay))
+        });
+
+        // Toggle PE to reset the peripheral state machine, configuration registers are kept
+        // SAFETY: I2C1 is owned by the HAL driver, this only touches CR1.PE between transfers
+        let i2c = unsafe { &*pac::I2C1::ptr() };
+        i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        while i2c.cr1.read().pe().bit_is_set() {}
+        let _ = i2c.isr.read();
+        let _ = i2c.isr.read();  // PE must stay low for at least 3 APB clocks
+        let _ = i2c.isr.read();
+        i2c.cr1.modify(|_, w| w.pe().set_bit());
+
+        released
+    }
+}
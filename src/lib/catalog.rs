@@ -0,0 +1,111 @@
+// catalog.rs
+// Common I2C parts, their candidate addresses and how to tell them apart
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+use core::fmt;
+
+use crate::chip::Chip;
+use crate::chip_map::RegAddr;
+
+// How a device proves what it is
+#[derive(Copy, Clone, Debug)]
+pub enum Identify {
+    Reg { reg: u16, mask: u8, value: u8 },  // 8-bit ID register
+    Reg16 { reg: u16, mask: u16, value: u16 },  // 16-bit big-endian ID register
+    Command { cmd: u16, wait_us: u32 },  // Any CRC-valid two word reply to a command, e.g. a serial number
+    Present,  // Nothing to read back, identified by address alone
+}
+
+pub struct KnownDevice {
+    pub name: &'static str,
+    pub addrs: &'static [u8],
+    pub id_name: &'static str,  // What the identifying value is called, e.g. chip_id
+    pub identify: Identify,
+    pub reg_addr: RegAddr,
+}
+
+impl KnownDevice {
+    pub fn chip<I2C: I2c>(&self, i2c: I2C, addr: u8) -> Chip<I2C> {
+        // Chip for this device, set up with its register address format
+        let mut chip = Chip::new_generic(i2c, addr);
+        chip.set_reg_addr(self.reg_addr);
+        chip
+    }
+
+    pub fn identify<I2C: I2c, D: DelayNs>(&self, i2c: &mut I2C, addr: u8, delay: &mut D) -> Option<u16> {
+        // Run the identification procedure, returns the identifying value if it matched
+        if !self.addrs.contains(&addr) {
+            return None;
+        }
+
+        let mut chip = self.chip(&mut *i2c, addr);
+        match self.identify {
+            Identify::Reg { reg, mask, value } => {
+                let id = chip.read_reg(reg).ok()?;
+                (id & mask == value).then_some(id as u16)
+            }
+            Identify::Reg16 { reg, mask, value } => {
+                let mut id_bytes = [0u8; 2];
+                chip.read_regs(reg, &mut id_bytes).ok()?;
+                let id = u16::from_be_bytes(id_bytes);
+                (id & mask == value).then_some(id)
+            }
+            Identify::Command { cmd, wait_us } => {
+                let mut words = [0u16; 2];
+                chip.read_command(cmd, wait_us, delay, &mut words).ok()?;
+                Some(words[0])
+            }
+            Identify::Present => Some(0),
+        }
+    }
+}
+
+// Checked in order, specific ID registers before heuristics and address-only matches
+pub static KNOWN_DEVICES: [KnownDevice; 9] = [
+    KnownDevice { name: "BME680", addrs: &[0x76, 0x77], id_name: "chip_id", identify: Identify::Reg { reg: 0xd0, mask: 0xff, value: 0x61 }, reg_addr: RegAddr::U8 },
+    KnownDevice { name: "BME280", addrs: &[0x76, 0x77], id_name: "chip_id", identify: Identify::Reg { reg: 0xd0, mask: 0xff, value: 0x60 }, reg_addr: RegAddr::U8 },
+    KnownDevice { name: "BMP280", addrs: &[0x76, 0x77], id_name: "chip_id", identify: Identify::Reg { reg: 0xd0, mask: 0xff, value: 0x58 }, reg_addr: RegAddr::U8 },
+    KnownDevice { name: "BMP280 sample", addrs: &[0x76, 0x77], id_name: "chip_id", identify: Identify::Reg { reg: 0xd0, mask: 0xfe, value: 0x56 }, reg_addr: RegAddr::U8 },  // 0x56 and 0x57
+    KnownDevice { name: "TMP117", addrs: &[0x48, 0x49, 0x4a, 0x4b], id_name: "device_id", identify: Identify::Reg16 { reg: 0x0f, mask: 0x0fff, value: 0x0117 }, reg_addr: RegAddr::U8 },
+    KnownDevice { name: "MCP9808", addrs: &[0x18, 0x19, 0x1a, 0x1b, 0x1c, 0x1d, 0x1e, 0x1f], id_name: "manufacturer_id", identify: Identify::Reg16 { reg: 0x06, mask: 0xffff, value: 0x0054 }, reg_addr: RegAddr::U8 },
+    KnownDevice {
+        name: "INA219",
+        addrs: &[0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f],
+        id_name: "config",
+        identify: Identify::Reg16 { reg: 0x00, mask: 0xffff, value: 0x399f },  // Power-on default, no ID register
+        reg_addr: RegAddr::U8,
+    },
+    // After INA219, which shares 0x44 to 0x46, so an INA219 is matched before it is sent the serial number command
+    KnownDevice { name: "SHT4x", addrs: &[0x44, 0x45, 0x46], id_name: "serial", identify: Identify::Command { cmd: 0x89, wait_us: 1_000 }, reg_addr: RegAddr::U8 },
+    // 8-bit word addresses only: 24LC01B, 24LC02B, and 24LC04B to 24LC16B which take the block from the low address bits
+    // 24LC32 and larger answer here too but need RegAddr::U16Be, presence alone can't tell them apart
+    KnownDevice { name: "24LC01B-16B EEPROM", addrs: &[0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57], id_name: "", identify: Identify::Present, reg_addr: RegAddr::U8 },
+];
+
+// A device matched against the catalog
+#[derive(Copy, Clone)]
+pub struct Identity {
+    pub device: &'static KnownDevice,
+    pub id: u16,
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.device.identify {
+            Identify::Present => write!(f, "{}", self.device.name),
+            Identify::Reg { .. } => write!(f, "{} ({} 0x{:02X})", self.device.name, self.device.id_name, self.id),
+            _ => write!(f, "{} ({} 0x{:04X})", self.device.name, self.device.id_name, self.id),
+        }
+    }
+}
+
+pub fn identify<I2C: I2c, D: DelayNs>(i2c: &mut I2C, addr: u8, delay: &mut D) -> Option<Identity> {
+    // First catalog entry whose identification procedure matches the device at `addr`
+    let old_level = log::max_level();
+    log::set_max_level(log::LevelFilter::Off);
+    let identity = KNOWN_DEVICES.iter()
+        .find_map(|device| device.identify(i2c, addr, delay).map(|id| Identity { device, id }));
+    log::set_max_level(old_level);
+
+    identity
+}
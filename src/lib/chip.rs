@@ -1,82 +1,172 @@
-use embedded_hal::blocking::i2c;
+use embedded_hal::delay::DelayNs;
 use core::marker::PhantomData;
-use log::{self, info};
-use crate::chip_map;
-
-/// Define some error types
-#[derive(Debug)]
-pub enum I2CError<I2C: i2c::WriteRead> {
-    NotFound,
-    I2CError(I2C::Error),
+use log::{self, info, warn};
+use crate::chip_map::{self, FieldMapProvider, RegAddr};
+use crate::shadow::ShadowCache;
+use crate::retry::{NoDelay, RetryPolicy, RetryStats};
+use crate::interface::{ReadSegments, RegisterInterface};
+
+pub use crate::error::I2CError;
+use crate::error::BusErrorKind;
+
+// Most buffers a single segmented read can scatter into
+pub const MAX_READ_SEGMENTS: usize = 8;
+
+// Longest register run compared in one read-back during write-verify
+const VERIFY_CHUNK_LEN: usize = 32;
+
+// Read back and compare each register write, retrying up to `retries` more times
+#[derive(Copy, Clone, Debug)]
+pub struct VerifyConfig {
+    pub retries: u8,
 }
 
-pub struct Chip<I2C, MAP=chip_map::NoFieldMap> {
+pub struct Chip<I2C, MAP=chip_map::NoFieldMap, DELAY=NoDelay> {
     pub i2c: I2C,
     pub i2c_addr: u8,
-    pub _map: PhantomData<MAP>,
+    pub reg_addr: RegAddr,
+    pub cache: Option<ShadowCache>,
+    pub verify: Option<VerifyConfig>,
+    pub pec: bool,
+    pub retry: RetryPolicy,
+    pub retry_stats: RetryStats,
+    pub delay: DELAY,
+    _map: PhantomData<MAP>,
 }
 
 impl<I2C> Chip<I2C, chip_map::NoFieldMap>
 where
-    I2C: i2c::WriteRead,
+    I2C: RegisterInterface,
 {
     pub fn new_generic(i2c: I2C, addr: u8) -> Self {
-        Self { i2c, i2c_addr: addr, _map: PhantomData }
+        Self::new(i2c, addr)
     }
 }
 
 impl<I2C, MAP> Chip<I2C, MAP>
 where
-    I2C: i2c::WriteRead,
+    I2C: RegisterInterface,
+    MAP: FieldMapProvider,
+{
+    pub fn new(i2c: I2C, addr: u8) -> Self {
+        Self {
+            i2c,
+            i2c_addr: addr,
+            reg_addr: MAP::reg_addr(),
+            cache: None,
+            verify: None,
+            pec: false,
+            retry: RetryPolicy::none(),
+            retry_stats: RetryStats::default(),
+            delay: NoDelay,
+            _map: PhantomData,
+        }
+    }
+}
+
+impl<I2C, MAP, DELAY> Chip<I2C, MAP, DELAY>
+where
+    I2C: RegisterInterface,
+    MAP: FieldMapProvider,
+    DELAY: DelayNs,
 {
+    pub fn with_retry<D: DelayNs>(self, retry: RetryPolicy, delay: D) -> Chip<I2C, MAP, D> {
+        // Attach a retry policy, along with the delay used between attempts
+        Chip {
+            i2c: self.i2c,
+            i2c_addr: self.i2c_addr,
+            reg_addr: self.reg_addr,
+            cache: self.cache,
+            verify: self.verify,
+            pec: self.pec,
+            retry,
+            retry_stats: self.retry_stats,
+            delay,
+            _map: PhantomData,
+        }
+    }
 
-    pub fn read_regs(&mut self, reg: u8, reg_values: &mut [u8]) -> Result<(), I2CError<I2C>> {
+    pub fn read_regs(&mut self, reg: u16, reg_values: &mut [u8]) -> Result<(), I2CError<I2C>> {
         // Basic function to read multiple registers
-        self.i2c.write_read(self.i2c_addr, &[reg], reg_values).map_err(I2CError::I2CError)?;
+        self.retrying(|this| this.bus_read(reg, reg_values))?;
 
-        let mut reg_idx = 0;
-        for reg_value in reg_values.iter() {
-            info!("Read Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg + reg_idx, reg_value, reg_value, reg_value);
-            reg_idx += 1;
+        for (reg_idx, reg_value) in reg_values.iter().enumerate() {
+            let reg_addr = self.reg_addr.offset(reg, reg_idx);
+            self.update_cache(reg_addr, *reg_value);
+            info!("Read Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg_addr, reg_value, reg_value, reg_value);
         }
 
         Ok(())
     }
 
-    pub fn write_reg(&mut self, reg: u8, reg_val: u8) -> Result<(), I2CError<I2C>> {
+    pub fn read_regs_into(&mut self, reg: u16, segments: &mut [&mut [u8]]) -> Result<(), I2CError<I2C>> {
+        // Read a run of registers in a single transaction, scattering it across several buffers
+        self.retrying(|this| this.bus_read_segments(reg, segments))?;
+
+        for (reg_idx, reg_value) in segments.iter().flat_map(|segment| segment.iter()).enumerate() {
+            let reg_addr = self.reg_addr.offset(reg, reg_idx);
+            self.update_cache(reg_addr, *reg_value);
+            info!("Read Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg_addr, reg_value, reg_value, reg_value);
+        }
+
+        Ok(())
+    }
+
+    pub fn write_reg(&mut self, reg: u16, reg_val: u8) -> Result<(), I2CError<I2C>> {
         // Basic function to write registers by numerical address
-        let mut buf = [0];
-        self.i2c.write_read(self.i2c_addr, &[reg, reg_val], &mut buf).map_err(I2CError::I2CError)?;
+        // Verified according to the chip's verify setting
+        self.write_reg_with(reg, reg_val, self.verify)
+    }
+
+    pub fn write_reg_with(&mut self, reg: u16, reg_val: u8, verify: Option<VerifyConfig>) -> Result<(), I2CError<I2C>> {
+        // Write a register, overriding the chip's verify setting for this call
+        let old_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+        let result = self.write_regs_with(reg, &[reg_val], verify);
+        log::set_max_level(old_level);
+        result?;
 
         info!("Write Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg, reg_val, reg_val, reg_val);
 
         Ok(())
     }
 
-    pub fn read_reg(&mut self, reg: u8) -> Result<u8, I2CError<I2C>> {
+    pub fn write_regs(&mut self, reg: u16, reg_values: &[u8]) -> Result<(), I2CError<I2C>> {
+        // Write a run of registers in a single transaction, relies on register auto-increment
+        self.write_regs_with(reg, reg_values, self.verify)
+    }
+
+    pub fn write_regs_with(&mut self, reg: u16, reg_values: &[u8], verify: Option<VerifyConfig>) -> Result<(), I2CError<I2C>> {
+        // Write a run of registers, overriding the chip's verify setting for this call
+        self.retrying(|this| this.write_verified(reg, reg_values, verify))?;
+
+        for (reg_idx, reg_value) in reg_values.iter().enumerate() {
+            let reg_addr = self.reg_addr.offset(reg, reg_idx);
+            self.update_cache(reg_addr, *reg_value);
+            info!("Write Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg_addr, reg_value, reg_value, reg_value);
+        }
+
+        Ok(())
+    }
+
+    pub fn read_reg(&mut self, reg: u16) -> Result<u8, I2CError<I2C>> {
         // Basic function to read registers by numerical address
         let mut reg_vals = [0];
         let old_level = log::max_level();
         log::set_max_level(log::LevelFilter::Off);
-        self.read_regs(reg, &mut reg_vals)?;
+        let result = self.read_regs(reg, &mut reg_vals);
         log::set_max_level(old_level);
+        result?;
 
         let reg_value = reg_vals[0];
         info!("Read Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg, reg_value, reg_value, reg_value);
 
         Ok(reg_value)
     }
-}
-
-impl<I2C, MAP> Chip<I2C, MAP>
-where
-    I2C: i2c::WriteRead,
-    MAP: chip_map::FieldMapProvider,
-{
 
     pub fn read_regs_str(&mut self, reg_str: &str, reg_values: &mut [u8]) -> Result<(), I2CError<I2C>> {
         // Basic function to read multiple registers
-        let reg_dets = MAP::get_field(reg_str).ok_or(I2CError::NotFound)?;
+        let reg_dets = MAP::get_field(reg_str).ok_or_else(|| I2CError::field_not_found(reg_str))?;
 
         // Just read the raw register value
         // let old_level = log::max_level();
@@ -92,13 +182,14 @@ where
 
     pub fn read_reg_str(&mut self, reg_str: &str) -> Result<u8, I2CError<I2C>> {
         // Basic function to read registers by name
-        let reg_dets = MAP::get_field(reg_str).ok_or(I2CError::NotFound)?;
+        let reg_dets = MAP::get_field(reg_str).ok_or_else(|| I2CError::field_not_found(reg_str))?;
 
         // Just read the raw register value
         let old_level = log::max_level();
         log::set_max_level(log::LevelFilter::Off);
-        let reg_value = self.read_reg(reg_dets.reg)?;
+        let reg_value = self.read_reg(reg_dets.reg);
         log::set_max_level(old_level);
+        let reg_value = reg_value?;
 
         info!("Read Register: {}, {:08b}, 0x{:.02X}, {}", reg_str, reg_value, reg_value, reg_value);
 
@@ -107,13 +198,14 @@ where
 
     pub fn write_reg_str(&mut self, reg_str: &str, reg_val: u8) -> Result<(), I2CError<I2C>> {
         // Basic function to write registers by name
-        let reg_dets = MAP::get_field(reg_str).ok_or(I2CError::NotFound)?;
+        let reg_dets = MAP::get_field(reg_str).ok_or_else(|| I2CError::field_not_found(reg_str))?;
 
         // Write the register
         let old_level = log::max_level();
         log::set_max_level(log::LevelFilter::Off);
-        self.write_reg(reg_dets.reg, reg_val)?;
+        let result = self.write_reg(reg_dets.reg, reg_val);
         log::set_max_level(old_level);
+        result?;
 
         info!("Write Register: {}, {:08b}, 0x{:.02X}, {}", reg_str, reg_val, reg_val, reg_val);
 
@@ -125,22 +217,19 @@ where
         // Will use a lookup table based on the field name
         
         // Get field details
-        let field_dets = MAP::get_field(field).ok_or(I2CError::NotFound)?;
-        let field_reg: u8 = field_dets.reg as u8;
-        let field_offset: u8 = field_dets.offset as u8;
-        let field_bits: u8 = field_dets.bits as u8;
+        let field_dets = MAP::get_field(field).ok_or_else(|| I2CError::field_not_found(field))?;
 
         // Read register
         let old_level = log::max_level();
         log::set_max_level(log::LevelFilter::Off);
-        let reg_val = self.read_reg(field_reg as u8)?;
+        let reg_val = self.read_reg(field_dets.reg);
         log::set_max_level(old_level);
+        let reg_val = reg_val.map_err(|err| err.with_field(field))?;
 
-        // Create mask and get value
-        let mask = (((1u32 << field_bits) - 1) << field_offset) as u8;
-        let field_val = (reg_val & mask) >> field_offset;
+        // Mask and shift out the field value
+        let field_val = field_dets.extract(reg_val);
 
-        info!("Read Field: {}, {:0width$b}, 0x{:.02X}, {}", field, field_val, field_val, field_val, width=field_bits as usize);
+        info!("Read Field: {}, {:0width$b}, 0x{:.02X}, {}", field, field_val, field_val, field_val, width=field_dets.bits as usize);
 
         Ok(field_val)
     }
@@ -148,33 +237,214 @@ where
     pub fn write_field(&mut self, field: &str, field_val: u8) -> Result<(), I2CError<I2C>> {
         // Basic function to write a field by name, within a register
         // Will use a lookup table based on the field name
+        self.write_field_with(field, field_val, self.verify)
+    }
 
-        // Get field details
-        let field_dets = MAP::get_field(field).ok_or(I2CError::NotFound)?;
-        let field_reg: u8 = field_dets.reg as u8;
-        let field_offset: u8 = field_dets.offset as u8;
-        let field_bits: u8 = field_dets.bits as u8;
-
-        let curr_field_val = self.read_reg(field_reg)?;
+    pub fn write_field_with(&mut self, field: &str, field_val: u8, verify: Option<VerifyConfig>) -> Result<(), I2CError<I2C>> {
+        // Write a field, overriding the chip's verify setting for this call
 
-        // Make mask of field_bits starting at field_offset
-        let mask = ((1u32 << field_bits) - 1) << field_offset;
+        // Get field details
+        let field_dets = MAP::get_field(field).ok_or_else(|| I2CError::field_not_found(field))?;
 
-        // Clear the bits in the current value
-        let cleared = (curr_field_val as u32) & !mask;
+        // Use the shadow copy of the register if there is one, otherwise read it
+        let curr_reg_val = match self.cached_reg(field_dets.reg) {
+            Some(reg_val) => reg_val,
+            None => self.read_reg(field_dets.reg).map_err(|err| err.with_field(field))?,
+        };
 
-        // Insert field_val into the correct position
-        let inserted = ((field_val as u32) << field_offset) & mask;
-        let field_val = (cleared | inserted) as u8;   // back to u8
+        // Clear the field bits and insert field_val into the correct position
+        let field_val = field_dets.insert(curr_reg_val, field_val);
     
         // Write register
         let old_level = log::max_level();
         log::set_max_level(log::LevelFilter::Off);
-        self.write_reg(field_reg, field_val)?;
+        let result = self.write_reg_with(field_dets.reg, field_val, verify);
         log::set_max_level(old_level);
+        result.map_err(|err| err.with_field(field))?;
+
+        info!("Write Field: {}, {:0width$b}, 0x{:.02X}, {}", field, field_val, field_val, field_val, width=field_dets.bits as usize);
+
+        Ok(())
+    }
+
+    pub fn set_reg_addr(&mut self, reg_addr: RegAddr) {
+        // Override the register address format given by the field map
+        self.reg_addr = reg_addr;
+    }
+
+    pub fn set_verify(&mut self, verify: Option<VerifyConfig>) {
+        // Default verify setting for every write on this chip
+        self.verify = verify;
+    }
+
+    pub fn enable_cache(&mut self) {
+        // Start shadowing non-volatile registers, beginning with an empty cache
+        if self.cache.is_none() {
+            self.cache = Some(ShadowCache::new());
+        }
+    }
 
-        info!("Write Field: {}, {:0width$b}, 0x{:.02X}, {}", field, field_val, field_val, field_val, width=field_bits as usize);
+    pub fn disable_cache(&mut self) {
+        self.cache = None;
+    }
+
+    pub fn invalidate(&mut self) {
+        // Forget every shadowed value, e.g. after a soft reset or brown-out
+        if let Some(cache) = self.cache.as_mut() {
+            cache.invalidate();
+        }
+    }
+
+    pub fn invalidate_reg(&mut self, reg: u16) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.invalidate_reg(reg);
+        }
+    }
+
+    pub fn sync_cache(&mut self) -> Result<(), I2CError<I2C>> {
+        // Reload the cache from the device, reads populate every cacheable register
+        self.invalidate();
+        self.dump()?;
 
         Ok(())
     }
+
+    pub(crate) fn retrying<T>(&mut self, mut op: impl FnMut(&mut Self) -> Result<T, I2CError<I2C>>) -> Result<T, I2CError<I2C>> {
+        // Run a bus access under the retry policy, counting retries and final failures
+        let mut attempt = 1;
+        loop {
+            let result = op(self);
+            self.track_bus_errors(&result);
+
+            match result {
+                Ok(val) => return Ok(val),
+                Err(err) if attempt < self.retry.max_attempts && self.retry.should_retry(&err) => {
+                    warn!("Retrying: 0x{:.02X}, attempt {} of {}", self.i2c_addr, attempt + 1, self.retry.max_attempts);
+                    self.retry_stats.retries += 1;
+                    attempt += 1;
+                    self.delay.delay_us(self.retry.delay_us);
+                }
+                Err(err) => {
+                    self.retry_stats.failures += 1;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    fn track_bus_errors<T>(&mut self, result: &Result<T, I2CError<I2C>>) {
+        // Count stuck-line symptoms in a row, recovering the bus once recover_after is reached
+        // A NACK means the bus itself works, an absent or busy device must not reset it under everyone else
+        match result {
+            Err(I2CError::Bus { kind: BusErrorKind::Bus | BusErrorKind::ArbitrationLost | BusErrorKind::Other, .. }) => {
+                self.retry_stats.bus_errors_in_row = self.retry_stats.bus_errors_in_row.saturating_add(1);
+            }
+            Ok(_) | Err(I2CError::Bus { kind: BusErrorKind::NackAddress | BusErrorKind::NackData | BusErrorKind::Nack, .. }) => {
+                self.retry_stats.bus_errors_in_row = 0;
+                return;
+            }
+            Err(_) => return,
+        }
+
+        if self.retry.recover_after == 0 || self.retry_stats.bus_errors_in_row < self.retry.recover_after {
+            return;
+        }
+
+        self.retry_stats.bus_errors_in_row = 0;
+        if self.i2c.recover_bus() {
+            self.retry_stats.recoveries += 1;
+            warn!("Bus Recovered: 0x{:.02X}", self.i2c_addr);
+        } else {
+            warn!("Bus Recovery Failed: 0x{:.02X}", self.i2c_addr);
+        }
+    }
+
+    fn bus_read(&mut self, reg: u16, reg_values: &mut [u8]) -> Result<(), I2CError<I2C>> {
+        // Single raw register read, no retries, caching or logging
+        let addr = self.i2c_addr;
+        let (reg_buf, reg_len) = self.reg_addr.encode(MAP::burst_reg(reg, reg_values.len()));
+        self.i2c.read_regs(addr, &reg_buf[..reg_len], reg_values)
+            .map_err(|source| I2CError::bus(addr, reg, source))
+    }
+
+    fn bus_read_segments(&mut self, reg: u16, segments: &mut [&mut [u8]]) -> Result<(), I2CError<I2C>> {
+        // Single raw transaction: register address, then consecutive reads without a restart
+        let mut bounded = ReadSegments::new();
+        for segment in segments.iter_mut() {
+            bounded.push(&mut segment[..]).map_err(|_| I2CError::TooManySegments)?;
+        }
+
+        let addr = self.i2c_addr;
+        let (reg_buf, reg_len) = self.reg_addr.encode(MAP::burst_reg(reg, bounded.iter().map(|segment| segment.len()).sum()));
+        self.i2c.read_regs_into(addr, &reg_buf[..reg_len], &mut bounded)
+            .map_err(|source| I2CError::bus(addr, reg, source))
+    }
+
+    fn bus_write(&mut self, reg: u16, reg_values: &[u8]) -> Result<(), I2CError<I2C>> {
+        // Single raw transaction: register address followed by the data
+        let addr = self.i2c_addr;
+        let (reg_buf, reg_len) = self.reg_addr.encode(MAP::burst_reg(reg, reg_values.len()));
+        self.i2c.write_regs(addr, &reg_buf[..reg_len], reg_values)
+            .map_err(|source| I2CError::bus(addr, reg, source))
+    }
+
+    fn write_verified(&mut self, reg: u16, reg_values: &[u8], verify: Option<VerifyConfig>) -> Result<(), I2CError<I2C>> {
+        // Raw register write, read back and compared when verify is set
+        let mut attempts_left = verify.map_or(0, |verify| verify.retries);
+        loop {
+            self.bus_write(reg, reg_values)?;
+
+            if verify.is_none() {
+                return Ok(());
+            }
+
+            match self.verify_regs(reg, reg_values)? {
+                None => return Ok(()),
+                Some((bad_reg, wrote, read)) => {
+                    warn!("Verify Mismatch: 0x{:.02X}, wrote 0x{:.02X}, read 0x{:.02X}", bad_reg, wrote, read);
+                    if attempts_left == 0 {
+                        self.invalidate_reg(bad_reg);
+                        return Err(I2CError::VerifyMismatch { reg: bad_reg, wrote, read });
+                    }
+                    attempts_left -= 1;
+                }
+            }
+        }
+    }
+
+    fn verify_regs(&mut self, reg: u16, reg_values: &[u8]) -> Result<Option<(u16, u8, u8)>, I2CError<I2C>> {
+        // Read back, ignoring bits the device is allowed to change on its own
+        // Returns the first mismatching register as (reg, wrote, read)
+        let mut chunk_reg = reg;
+        for chunk in reg_values.chunks(VERIFY_CHUNK_LEN) {
+            let mut read_buf = [0u8; VERIFY_CHUNK_LEN];
+            let read_vals = &mut read_buf[..chunk.len()];
+            self.bus_read(chunk_reg, read_vals)?;
+
+            for (reg_idx, (wrote, read)) in chunk.iter().zip(read_vals.iter()).enumerate() {
+                let reg_addr = self.reg_addr.offset(chunk_reg, reg_idx);
+                if (wrote ^ read) & MAP::reg_verify_mask(reg_addr) != 0 {
+                    return Ok(Some((reg_addr, *wrote, *read)));
+                }
+            }
+            chunk_reg = self.reg_addr.offset(chunk_reg, chunk.len());
+        }
+
+        Ok(None)
+    }
+
+    pub(crate) fn cached_reg(&self, reg: u16) -> Option<u8> {
+        if !MAP::reg_cacheable(reg) {
+            return None;
+        }
+        self.cache.as_ref().and_then(|cache| cache.get(reg))
+    }
+
+    fn update_cache(&mut self, reg: u16, reg_val: u8) {
+        if let Some(cache) = self.cache.as_mut() {
+            if MAP::reg_cacheable(reg) {
+                cache.update(reg, reg_val);
+            }
+        }
+    }
 }
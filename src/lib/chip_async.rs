@@ -0,0 +1,113 @@
+// chip_async.rs
+use embedded_hal_async::i2c::{I2c, Operation};
+use core::marker::PhantomData;
+use log::info;
+use crate::chip_map::{self, FieldMapProvider, RegAddr};
+
+pub use crate::error::I2CError;
+
+// Async counterpart of Chip, sharing the same field maps and error type
+// Plain register and field access, no caching, verify or retries
+pub struct AsyncChip<I2C, MAP=chip_map::NoFieldMap> {
+    pub i2c: I2C,
+    pub i2c_addr: u8,
+    pub reg_addr: RegAddr,
+    _map: PhantomData<MAP>,
+}
+
+impl<I2C, MAP> AsyncChip<I2C, MAP>
+where
+    I2C: I2c,
+    MAP: FieldMapProvider,
+{
+    pub fn new(i2c: I2C, addr: u8) -> Self {
+        Self { i2c, i2c_addr: addr, reg_addr: MAP::reg_addr(), _map: PhantomData }
+    }
+
+    pub(crate) async fn bus_read(&mut self, reg: u16, reg_values: &mut [u8]) -> Result<(), I2CError<I2C>> {
+        // Raw read without logging, the log level is global so it can't be muted across an await
+        let addr = self.i2c_addr;
+        let (reg_buf, reg_len) = self.reg_addr.encode(MAP::burst_reg(reg, reg_values.len()));
+        self.i2c.transaction(addr, &mut [Operation::Write(&reg_buf[..reg_len]), Operation::Read(reg_values)]).await
+            .map_err(|source| I2CError::bus(addr, reg, source))
+    }
+
+    pub(crate) async fn bus_write(&mut self, reg: u16, reg_values: &[u8]) -> Result<(), I2CError<I2C>> {
+        // Raw write without logging
+        let addr = self.i2c_addr;
+        let (reg_buf, reg_len) = self.reg_addr.encode(MAP::burst_reg(reg, reg_values.len()));
+        self.i2c.transaction(addr, &mut [Operation::Write(&reg_buf[..reg_len]), Operation::Write(reg_values)]).await
+            .map_err(|source| I2CError::bus(addr, reg, source))
+    }
+
+    pub async fn read_regs(&mut self, reg: u16, reg_values: &mut [u8]) -> Result<(), I2CError<I2C>> {
+        // Basic function to read multiple registers
+        self.bus_read(reg, reg_values).await?;
+
+        for (reg_idx, reg_value) in reg_values.iter().enumerate() {
+            let reg_addr = self.reg_addr.offset(reg, reg_idx);
+            info!("Read Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg_addr, reg_value, reg_value, reg_value);
+        }
+
+        Ok(())
+    }
+
+    pub async fn write_regs(&mut self, reg: u16, reg_values: &[u8]) -> Result<(), I2CError<I2C>> {
+        // Write a run of registers in a single transaction, relies on register auto-increment
+        self.bus_write(reg, reg_values).await?;
+
+        for (reg_idx, reg_value) in reg_values.iter().enumerate() {
+            let reg_addr = self.reg_addr.offset(reg, reg_idx);
+            info!("Write Register: 0x{:.02X}, {:08b}, 0x{:.02X}, {}", reg_addr, reg_value, reg_value, reg_value);
+        }
+
+        Ok(())
+    }
+
+    pub async fn read_reg(&mut self, reg: u16) -> Result<u8, I2CError<I2C>> {
+        // Basic function to read registers by numerical address
+        let mut reg_vals = [0];
+        self.read_regs(reg, &mut reg_vals).await?;
+        Ok(reg_vals[0])
+    }
+
+    pub async fn write_reg(&mut self, reg: u16, reg_val: u8) -> Result<(), I2CError<I2C>> {
+        // Basic function to write registers by numerical address
+        self.write_regs(reg, &[reg_val]).await
+    }
+
+    pub async fn read_regs_str(&mut self, reg_str: &str, reg_values: &mut [u8]) -> Result<(), I2CError<I2C>> {
+        // Read multiple registers, starting at the register holding a named field
+        let reg_dets = MAP::get_field(reg_str).ok_or_else(|| I2CError::field_not_found(reg_str))?;
+        self.read_regs(reg_dets.reg, reg_values).await
+    }
+
+    pub async fn read_field(&mut self, field: &str) -> Result<u8, I2CError<I2C>> {
+        // Basic function to read a field by name, within a register
+        let field_dets = MAP::get_field(field).ok_or_else(|| I2CError::field_not_found(field))?;
+
+        let mut reg_val = [0];
+        self.bus_read(field_dets.reg, &mut reg_val).await.map_err(|err| err.with_field(field))?;
+        let reg_val = reg_val[0];
+
+        let field_val = field_dets.extract(reg_val);
+
+        info!("Read Field: {}, {:0width$b}, 0x{:.02X}, {}", field, field_val, field_val, field_val, width=field_dets.bits as usize);
+
+        Ok(field_val)
+    }
+
+    pub async fn write_field(&mut self, field: &str, field_val: u8) -> Result<(), I2CError<I2C>> {
+        // Basic function to write a field by name, read-modify-write of its register
+        let field_dets = MAP::get_field(field).ok_or_else(|| I2CError::field_not_found(field))?;
+
+        let mut curr_reg_val = [0];
+        self.bus_read(field_dets.reg, &mut curr_reg_val).await.map_err(|err| err.with_field(field))?;
+        self.bus_write(field_dets.reg, &[field_dets.insert(curr_reg_val[0], field_val)]).await
+            .map_err(|err| err.with_field(field))?;
+
+        info!("Write Field: {}, {:0width$b}, 0x{:.02X}, {}", field, field_val, field_val, field_val, width=field_dets.bits as usize);
+
+        Ok(())
+    }
+}
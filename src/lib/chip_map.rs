@@ -1,22 +1,211 @@
 // chip_map.rs
 
+use heapless::Vec;
+use phf::Map;
+use phf_macros::phf_map;
+
+// Upper bound on the number of distinct registers a field map can reference
+pub const MAX_MAP_REGS: usize = 128;
+
 pub struct Field {
-    pub reg: u8,
+    pub reg: u16,
     pub offset: u8,
     pub bits: u8,
     pub writable: bool,
+    pub volatile: bool,  // Changed by the device itself (status, data, self-clearing bits)
+}
+
+impl Field {
+    pub fn mask(&self) -> u8 {
+        // Mask of field_bits starting at field_offset
+        (((1u32 << self.bits) - 1) << self.offset) as u8
+    }
+
+    pub fn extract(&self, reg_val: u8) -> u8 {
+        // Pull the field value out of a raw register value
+        (reg_val & self.mask()) >> self.offset
+    }
+
+    pub fn insert(&self, reg_val: u8, field_val: u8) -> u8 {
+        // Replace the field bits in a raw register value
+        let cleared = reg_val & !self.mask();
+        let inserted = ((field_val as u32) << self.offset) as u8 & self.mask();
+        cleared | inserted
+    }
+}
+
+// Longest register address sent on the bus
+pub const MAX_REG_ADDR_LEN: usize = 2;
+
+// Width and byte order of the register address sent before the data
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RegAddr {
+    U8,
+    U16Be,  // Most significant byte first, e.g. 24LC EEPROMs, VL53L1X
+    U16Le,
+}
+
+impl RegAddr {
+    pub fn encode(self, reg: u16) -> ([u8; MAX_REG_ADDR_LEN], usize) {
+        // Address bytes as sent on the bus, along with how many are used
+        match self {
+            RegAddr::U8 => ([reg as u8, 0], 1),
+            RegAddr::U16Be => (reg.to_be_bytes(), 2),
+            RegAddr::U16Le => (reg.to_le_bytes(), 2),
+        }
+    }
+
+    pub fn offset(self, reg: u16, offset: usize) -> u16 {
+        // Address `offset` registers on from `reg`, wrapping at the address width
+        match self {
+            RegAddr::U8 => (reg as u8).wrapping_add(offset as u8) as u16,
+            RegAddr::U16Be | RegAddr::U16Le => reg.wrapping_add(offset as u16),
+        }
+    }
+}
+
+// A single field decoded from a raw register value
+pub struct FieldValue {
+    pub name: &'static str,
+    pub field: &'static Field,
+    pub value: u8,
 }
 
 // Trait for field map providers
 pub trait FieldMapProvider {
-    fn get_field(name: &str) -> Option<&'static Field>;
+    fn map() -> &'static Map<&'static str, Field>;
+
+    fn reg_addr() -> RegAddr {
+        // Register address format, most parts use a single byte
+        RegAddr::U8
+    }
+
+    fn auto_increment() -> u16 {
+        // Bits set in the register address to enable auto-increment for multi-register transfers
+        // e.g. 0x80 on ST sensors such as the LIS3DH
+        0
+    }
+
+    fn burst_reg(reg: u16, len: usize) -> u16 {
+        // Register address as sent for a transfer of `len` registers
+        if len > 1 { reg | Self::auto_increment() } else { reg }
+    }
+
+    fn get_field(name: &str) -> Option<&'static Field> {
+        Self::map().get(name)
+    }
+
+    fn fields() -> impl Iterator<Item = (&'static str, &'static Field)> {
+        // Iterate over every named field in the map
+        Self::map().entries().map(|(name, field)| (*name, field))
+    }
+
+    fn fields_in_reg(reg: u16) -> impl Iterator<Item = (&'static str, &'static Field)> {
+        // Reverse lookup - every field that lives in the given register
+        Self::fields().filter(move |(_, field)| field.reg == reg)
+    }
+
+    fn decode(reg: u16, reg_val: u8) -> impl Iterator<Item = FieldValue> {
+        // Break a raw register value down into its named fields
+        Self::fields_in_reg(reg).map(move |(name, field)| FieldValue { name, field, value: field.extract(reg_val) })
+    }
+
+    fn regs() -> Option<Vec<u16, MAX_MAP_REGS>> {
+        // Every register referenced by the map, sorted and without duplicates
+        // None if the map references more than MAX_MAP_REGS registers
+        let mut regs: Vec<u16, MAX_MAP_REGS> = Vec::new();
+        for (_, field) in Self::fields() {
+            if !regs.contains(&field.reg) {
+                regs.push(field.reg).ok()?;
+            }
+        }
+        regs.sort_unstable();
+        Some(regs)
+    }
+
+    fn reg_writable(reg: u16) -> bool {
+        // A register is only writable if every field mapped into it is writable
+        let mut fields = Self::fields_in_reg(reg).peekable();
+        fields.peek().is_some() && fields.all(|(_, field)| field.writable)
+    }
+
+    fn reg_volatile(reg: u16) -> bool {
+        // A register is volatile if any field mapped into it is volatile
+        Self::fields_in_reg(reg).any(|(_, field)| field.volatile)
+    }
+
+    fn reg_verify_mask(reg: u16) -> u8 {
+        // Bits that must read back as written, volatile fields are masked out
+        Self::fields_in_reg(reg)
+            .filter(|(_, field)| field.volatile)
+            .fold(0xff, |mask, (_, field)| mask & !field.mask())
+    }
+
+    fn reg_cacheable(reg: u16) -> bool {
+        // Only writable, non-volatile registers are worth shadowing
+        Self::reg_writable(reg) && !Self::reg_volatile(reg)
+    }
 }
 
 // Default case where no field map is provided
 pub struct NoFieldMap;
 
+static NO_FIELD_MAP: Map<&'static str, Field> = phf_map! {};
+
 impl FieldMapProvider for NoFieldMap {
-    fn get_field(_name: &str) -> Option<&'static Field> {
-        None
+    fn map() -> &'static Map<&'static str, Field> {
+        &NO_FIELD_MAP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODE: Field = Field { reg: 0x74, offset: 0, bits: 2, writable: true, volatile: false };
+    const OSRS_T: Field = Field { reg: 0x74, offset: 5, bits: 3, writable: true, volatile: false };
+    const FULL: Field = Field { reg: 0x00, offset: 0, bits: 8, writable: true, volatile: false };
+
+    #[test]
+    fn extract_reads_only_the_field_bits() {
+        assert_eq!(OSRS_T.extract(0b1011_1101), 0b101);
+        assert_eq!(MODE.extract(0b1011_1101), 0b01);
+        assert_eq!(FULL.extract(0xa5), 0xa5);
+    }
+
+    #[test]
+    fn insert_keeps_the_other_bits() {
+        assert_eq!(OSRS_T.insert(0b0001_1111, 0b101), 0b1011_1111);
+        assert_eq!(OSRS_T.insert(0xff, 0), 0b0001_1111);
+        assert_eq!(MODE.insert(0b1010_1000, 0b11), 0b1010_1011);
+        assert_eq!(FULL.insert(0x12, 0x34), 0x34);
+    }
+
+    #[test]
+    fn insert_truncates_values_wider_than_the_field() {
+        assert_eq!(MODE.insert(0x00, 0xff), 0b11);
+        assert_eq!(OSRS_T.insert(0x00, 0b1111), 0b1110_0000);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn insert_then_extract_round_trips() {
+        for value in 0..8 {
+            assert_eq!(OSRS_T.extract(OSRS_T.insert(0x5a, value)), value);
+        }
+    }
+
+    #[test]
+    fn reg_addr_encoding() {
+        assert_eq!(RegAddr::U8.encode(0x1234), ([0x34, 0], 1));
+        assert_eq!(RegAddr::U16Be.encode(0x1234), ([0x12, 0x34], 2));
+        assert_eq!(RegAddr::U16Le.encode(0x1234), ([0x34, 0x12], 2));
+    }
+
+    #[test]
+    fn reg_addr_offset_wraps_at_the_address_width() {
+        assert_eq!(RegAddr::U8.offset(0x10, 3), 0x13);
+        assert_eq!(RegAddr::U8.offset(0xff, 1), 0x00);
+        assert_eq!(RegAddr::U16Be.offset(0x00ff, 1), 0x0100);
+        assert_eq!(RegAddr::U16Le.offset(0xffff, 2), 0x0001);
+    }
+}
@@ -0,0 +1,102 @@
+// command.rs
+// Command mode on top of Chip, for parts such as the Sensirion SHT4x and SCD4x
+// Commands are sent in the chip's register address format, replies are 16-bit words each followed by a CRC
+use embedded_hal::delay::DelayNs;
+use log::{self, info};
+
+use crate::chip::{Chip, I2CError};
+use crate::chip_map::FieldMapProvider;
+use crate::interface::RegisterInterface;
+use crate::smbus::crc8;
+
+// Most words a single command can send or receive
+pub const MAX_COMMAND_WORDS: usize = 16;
+
+pub fn word_crc(word: u16) -> u8 {
+    // Sensirion CRC-8, polynomial x^8 + x^5 + x^4 + 1 with an initial value of 0xFF
+    crc8(0x31, 0xff, &word.to_be_bytes())
+}
+
+impl<I2C, MAP, DELAY> Chip<I2C, MAP, DELAY>
+where
+    I2C: RegisterInterface,
+    MAP: FieldMapProvider,
+    DELAY: DelayNs,
+{
+    pub fn send_command(&mut self, cmd: u16) -> Result<(), I2CError<I2C>> {
+        // Command without arguments, e.g. a soft reset or start of a periodic measurement
+        self.write_command(cmd, &[])
+    }
+
+    pub fn write_command(&mut self, cmd: u16, words: &[u16]) -> Result<(), I2CError<I2C>> {
+        // Command followed by argument words, each with its CRC
+        let addr = self.i2c_addr;
+        if words.len() > MAX_COMMAND_WORDS {
+            return Err(I2CError::TooManyWords { addr, cmd, len: words.len() });
+        }
+
+        let mut buf = [0u8; 3 * MAX_COMMAND_WORDS];
+        for (chunk, word) in buf.chunks_mut(3).zip(words.iter()) {
+            chunk[..2].copy_from_slice(&word.to_be_bytes());
+            chunk[2] = word_crc(*word);
+        }
+        let data = &buf[..3 * words.len()];
+
+        let (cmd_buf, cmd_len) = self.reg_addr.encode(cmd);
+        self.retrying(|this| {
+            this.i2c.write_regs(addr, &cmd_buf[..cmd_len], data)
+                .map_err(|source| I2CError::bus(addr, cmd, source))
+        })?;
+        info!("Write Command: 0x{:.04X}, {} words", cmd, words.len());
+
+        Ok(())
+    }
+
+    pub fn read_command<D: DelayNs>(&mut self, cmd: u16, wait_us: u32, delay: &mut D, words: &mut [u16]) -> Result<(), I2CError<I2C>> {
+        // Send a command, wait for the device to execute it, then read and check the reply words
+        let addr = self.i2c_addr;
+        if words.len() > MAX_COMMAND_WORDS {
+            return Err(I2CError::TooManyWords { addr, cmd, len: words.len() });
+        }
+
+        let old_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+        let result = self.send_command(cmd);
+        log::set_max_level(old_level);
+        result?;
+
+        delay.delay_us(wait_us);
+        self.read_words(cmd, words)?;
+        for word in words.iter() {
+            info!("Read Word: 0x{:.04X}, 0x{:.04X}, {}", cmd, word, word);
+        }
+
+        Ok(())
+    }
+
+    fn read_words(&mut self, cmd: u16, words: &mut [u16]) -> Result<(), I2CError<I2C>> {
+        // Reply of the last command, each word is checked against its CRC
+        let addr = self.i2c_addr;
+        let mut buf = [0u8; 3 * MAX_COMMAND_WORDS];
+        let data = &mut buf[..3 * words.len()];
+
+        self.retrying(|this| {
+            this.i2c.read_raw(addr, data).map_err(|source| I2CError::bus(addr, cmd, source))?;
+
+            for chunk in data.chunks(3) {
+                let expected = word_crc(u16::from_be_bytes([chunk[0], chunk[1]]));
+                if expected != chunk[2] {
+                    return Err(I2CError::CrcMismatch { addr, cmd, expected, read: chunk[2] });
+                }
+            }
+
+            Ok(())
+        })?;
+
+        for (word, chunk) in words.iter_mut().zip(data.chunks(3)) {
+            *word = u16::from_be_bytes([chunk[0], chunk[1]]);
+        }
+
+        Ok(())
+    }
+}
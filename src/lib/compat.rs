@@ -0,0 +1,342 @@
+// compat.rs
+// Adapters for HALs that only implement embedded-hal 0.2, such as stm32h7xx-hal 0.16
+// Board and HAL specific pieces live in board.rs
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital;
+use embedded_hal::i2c::{self, ErrorKind, Operation};
+use embedded_hal_02::blocking::delay::DelayUs;
+use embedded_hal_02::blocking::i2c as i2c_02;
+use embedded_hal_02::digital::v2 as digital_02;
+use embedded_hal_02::serial as serial_02;
+
+use crate::telemetry::FrameWriter;
+
+// Longest run of consecutive writes or reads the adapter can merge into one transfer
+pub const MAX_ADAPTER_BUF: usize = 64;
+
+// Map a HAL specific embedded-hal 0.2 error onto an embedded-hal 1.0 error kind
+pub trait ClassifyError {
+    fn kind(&self) -> ErrorKind;
+}
+
+#[derive(Debug)]
+pub enum Eh02Error<E> {
+    I2C(E),
+    BufferTooSmall,  // Merged writes or reads did not fit in MAX_ADAPTER_BUF
+    Unsupported,  // Zero-length transfer, which 0.2 HALs cannot issue
+}
+
+impl<E> i2c::Error for Eh02Error<E>
+where
+    E: ClassifyError + core::fmt::Debug,
+{
+    fn kind(&self) -> ErrorKind {
+        match self {
+            Eh02Error::I2C(err) => err.kind(),
+            Eh02Error::BufferTooSmall => ErrorKind::Overrun,
+            Eh02Error::Unsupported => ErrorKind::Other,
+        }
+    }
+}
+
+// Wraps an embedded-hal 0.2 blocking I2C bus so it implements the 1.0 I2c trait
+pub struct Eh02I2c<I2C> {
+    pub i2c: I2C,
+}
+
+impl<I2C> Eh02I2c<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        Self { i2c }
+    }
+
+    pub fn release(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C, E> i2c::ErrorType for Eh02I2c<I2C>
+where
+    I2C: i2c_02::Write<Error = E> + i2c_02::Read<Error = E> + i2c_02::WriteRead<Error = E>,
+    E: ClassifyError + core::fmt::Debug,
+{
+    type Error = Eh02Error<E>;
+}
+
+impl<I2C, E> i2c::I2c for Eh02I2c<I2C>
+where
+    I2C: i2c_02::Write<Error = E> + i2c_02::Read<Error = E> + i2c_02::WriteRead<Error = E>,
+    E: ClassifyError + core::fmt::Debug,
+{
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        // 0.2 has no transaction support, so each run of writes followed by a run of reads
+        // becomes one write, read or write_read. Runs are merged through a local buffer.
+        let mut op_idx = 0;
+        while op_idx < operations.len() {
+            let run_start = op_idx;
+
+            // Gather consecutive writes
+            let mut write_buf = [0u8; MAX_ADAPTER_BUF];
+            let mut write_len = 0;
+            while let Some(Operation::Write(bytes)) = operations.get(op_idx) {
+                let end = write_len + bytes.len();
+                write_buf.get_mut(write_len..end).ok_or(Eh02Error::BufferTooSmall)?.copy_from_slice(bytes);
+                write_len = end;
+                op_idx += 1;
+            }
+
+            // Gather consecutive reads
+            let read_start = op_idx;
+            let mut read_len = 0;
+            while let Some(Operation::Read(bytes)) = operations.get(op_idx) {
+                read_len += bytes.len();
+                op_idx += 1;
+            }
+            if read_len > MAX_ADAPTER_BUF {
+                return Err(Eh02Error::BufferTooSmall);
+            }
+
+            let mut read_buf = [0u8; MAX_ADAPTER_BUF];
+            let write = &write_buf[..write_len];
+            let read = &mut read_buf[..read_len];
+            match (write.is_empty(), read.is_empty()) {
+                (false, false) => self.i2c.write_read(address, write, read).map_err(Eh02Error::I2C)?,
+                (false, true) => self.i2c.write(address, write).map_err(Eh02Error::I2C)?,
+                (true, false) => self.i2c.read(address, read).map_err(Eh02Error::I2C)?,
+                // Only empty operations in this run, e.g. a zero-length write used as a probe
+                (true, true) if op_idx > run_start => return Err(Eh02Error::Unsupported),
+                (true, true) => {}
+            }
+
+            // Scatter the merged read back into each segment
+            let mut read_offset = 0;
+            for operation in operations[read_start..op_idx].iter_mut() {
+                if let Operation::Read(bytes) = operation {
+                    bytes.copy_from_slice(&read[read_offset..read_offset + bytes.len()]);
+                    read_offset += bytes.len();
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Wraps an embedded-hal 0.2 microsecond delay so it implements the 1.0 DelayNs trait
+pub struct Eh02Delay<D> {
+    pub delay: D,
+}
+
+impl<D> Eh02Delay<D> {
+    pub fn new(delay: D) -> Self {
+        Self { delay }
+    }
+}
+
+impl<D> DelayNs for Eh02Delay<D>
+where
+    D: DelayUs<u32>,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        // Round up so a delay is never shorter than asked for
+        self.delay.delay_us(ns.div_ceil(1_000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us);
+    }
+}
+
+// Wraps an embedded-hal 0.2 serial transmitter, e.g. a HAL UART Tx, so it can carry telemetry frames
+pub struct Eh02Serial<TX> {
+    pub tx: TX,
+}
+
+impl<TX> Eh02Serial<TX> {
+    pub fn new(tx: TX) -> Self {
+        Self { tx }
+    }
+}
+
+impl<TX> FrameWriter for Eh02Serial<TX>
+where
+    TX: serial_02::Write<u8>,
+{
+    fn write_frame(&mut self, frame: &[u8]) -> bool {
+        // Blocks per byte, a UART error drops the rest of the frame
+        for &byte in frame {
+            if nb::block!(self.tx.write(byte)).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Borrows an embedded-hal 0.2 pin so it implements the 1.0 digital traits
+pub struct Eh02Pin<'a, P> {
+    pub pin: &'a mut P,
+}
+
+impl<'a, P> Eh02Pin<'a, P> {
+    pub fn new(pin: &'a mut P) -> Self {
+        Self { pin }
+    }
+}
+
+impl<P> digital::ErrorType for Eh02Pin<'_, P> {
+    type Error = digital::ErrorKind;
+}
+
+impl<P> digital::OutputPin for Eh02Pin<'_, P>
+where
+    P: digital_02::OutputPin,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_low().map_err(|_| digital::ErrorKind::Other)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.pin.set_high().map_err(|_| digital::ErrorKind::Other)
+    }
+}
+
+impl<P> digital::InputPin for Eh02Pin<'_, P>
+where
+    P: digital_02::InputPin,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        digital_02::InputPin::is_high(self.pin).map_err(|_| digital::ErrorKind::Other)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        digital_02::InputPin::is_low(self.pin).map_err(|_| digital::ErrorKind::Other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_hal::i2c::I2c;
+    use heapless::Vec;
+
+    #[derive(Debug)]
+    struct MockError;
+
+    impl ClassifyError for MockError {
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    // What the 0.2 bus was asked to do, reads are answered with 1, 2, 3, ...
+    #[derive(Debug, PartialEq)]
+    enum Call {
+        Write(Vec<u8, MAX_ADAPTER_BUF>),
+        Read(usize),
+        WriteRead(Vec<u8, MAX_ADAPTER_BUF>, usize),
+    }
+
+    #[derive(Default)]
+    struct MockBus {
+        calls: Vec<Call, 8>,
+    }
+
+    fn bytes(data: &[u8]) -> Vec<u8, MAX_ADAPTER_BUF> {
+        Vec::from_slice(data).unwrap()
+    }
+
+    fn fill(buffer: &mut [u8]) {
+        for (idx, byte) in buffer.iter_mut().enumerate() {
+            *byte = idx as u8 + 1;
+        }
+    }
+
+    impl i2c_02::Write for MockBus {
+        type Error = MockError;
+
+        fn write(&mut self, _address: u8, data: &[u8]) -> Result<(), Self::Error> {
+            let _ = self.calls.push(Call::Write(bytes(data)));
+            Ok(())
+        }
+    }
+
+    impl i2c_02::Read for MockBus {
+        type Error = MockError;
+
+        fn read(&mut self, _address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+            fill(buffer);
+            let _ = self.calls.push(Call::Read(buffer.len()));
+            Ok(())
+        }
+    }
+
+    impl i2c_02::WriteRead for MockBus {
+        type Error = MockError;
+
+        fn write_read(&mut self, _address: u8, data: &[u8], buffer: &mut [u8]) -> Result<(), Self::Error> {
+            fill(buffer);
+            let _ = self.calls.push(Call::WriteRead(bytes(data), buffer.len()));
+            Ok(())
+        }
+    }
+
+    fn adapter() -> Eh02I2c<MockBus> {
+        Eh02I2c::new(MockBus::default())
+    }
+
+    #[test]
+    fn consecutive_writes_become_one_write() {
+        let mut i2c = adapter();
+        i2c.transaction(0x76, &mut [Operation::Write(&[0x74]), Operation::Write(&[0xb4, 0x08])]).unwrap();
+
+        assert_eq!(i2c.i2c.calls.as_slice(), &[Call::Write(bytes(&[0x74, 0xb4, 0x08]))]);
+    }
+
+    #[test]
+    fn reads_after_a_write_become_one_write_read_and_are_scattered_back() {
+        let mut i2c = adapter();
+        let (mut first, mut second) = ([0u8; 2], [0u8; 3]);
+        i2c.transaction(0x76, &mut [Operation::Write(&[0x1d]), Operation::Read(&mut first), Operation::Read(&mut second)]).unwrap();
+
+        assert_eq!(i2c.i2c.calls.as_slice(), &[Call::WriteRead(bytes(&[0x1d]), 5)]);
+        assert_eq!((first, second), ([1, 2], [3, 4, 5]));
+    }
+
+    #[test]
+    fn a_write_after_a_read_starts_a_new_transfer() {
+        let mut i2c = adapter();
+        let mut read = [0u8; 1];
+        i2c.transaction(0x76, &mut [Operation::Write(&[0xd0]), Operation::Read(&mut read), Operation::Write(&[0xe0, 0xb6])]).unwrap();
+
+        assert_eq!(i2c.i2c.calls.as_slice(), &[Call::WriteRead(bytes(&[0xd0]), 1), Call::Write(bytes(&[0xe0, 0xb6]))]);
+    }
+
+    #[test]
+    fn plain_reads_become_one_read() {
+        let mut i2c = adapter();
+        let mut read = [0u8; 3];
+        i2c.read(0x44, &mut read).unwrap();
+
+        assert_eq!(i2c.i2c.calls.as_slice(), &[Call::Read(3)]);
+        assert_eq!(read, [1, 2, 3]);
+    }
+
+    #[test]
+    fn zero_length_transfer_is_unsupported() {
+        let mut i2c = adapter();
+        let result = i2c.transaction(0x76, &mut [Operation::Write(&[])]);
+
+        assert!(matches!(result, Err(Eh02Error::Unsupported)));
+        assert!(i2c.i2c.calls.is_empty());
+    }
+
+    #[test]
+    fn merged_writes_longer_than_the_buffer_are_rejected() {
+        let mut i2c = adapter();
+        let data = [0u8; MAX_ADAPTER_BUF];
+        let result = i2c.transaction(0x50, &mut [Operation::Write(&[0x00]), Operation::Write(&data)]);
+
+        assert!(matches!(result, Err(Eh02Error::BufferTooSmall)));
+        assert!(i2c.i2c.calls.is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+// error.rs
+use embedded_hal::i2c::{self, Error as _, ErrorKind, NoAcknowledgeSource};
+use core::fmt;
+use heapless::String;
+
+// Longest field name kept in an error, longer names are truncated
+pub const MAX_FIELD_NAME_LEN: usize = 24;
+
+pub type FieldName = String<MAX_FIELD_NAME_LEN>;
+
+// What went wrong on the bus, independent of the HAL's error type
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BusErrorKind {
+    NackAddress,  // No device answered the address
+    NackData,  // The device stopped acknowledging mid-transfer
+    Nack,  // NACK where the HAL cannot tell address from data
+    ArbitrationLost,
+    Bus,  // Misplaced START/STOP, usually noise or a stuck line
+    Overrun,
+    Other,
+}
+
+impl fmt::Display for BusErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let desc = match self {
+            BusErrorKind::NackAddress => "NACK on address",
+            BusErrorKind::NackData => "NACK on data",
+            BusErrorKind::Nack => "NACK",
+            BusErrorKind::ArbitrationLost => "arbitration lost",
+            BusErrorKind::Bus => "bus error",
+            BusErrorKind::Overrun => "overrun",
+            BusErrorKind::Other => "I2C error",
+        };
+        f.write_str(desc)
+    }
+}
+
+impl From<ErrorKind> for BusErrorKind {
+    fn from(kind: ErrorKind) -> Self {
+        match kind {
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => BusErrorKind::NackAddress,
+            ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data) => BusErrorKind::NackData,
+            ErrorKind::NoAcknowledge(_) => BusErrorKind::Nack,
+            ErrorKind::ArbitrationLoss => BusErrorKind::ArbitrationLost,
+            ErrorKind::Bus => BusErrorKind::Bus,
+            ErrorKind::Overrun => BusErrorKind::Overrun,
+            _ => BusErrorKind::Other,
+        }
+    }
+}
+
+/// Define some error types
+pub enum I2CError<I2C: i2c::ErrorType> {
+    FieldNotFound { field: FieldName },
+    BatchFull { field: FieldName },
+    FieldReadOnly { field: FieldName },
+    TooManySegments,
+    TooManyRegisters,
+    VerifyMismatch { reg: u16, wrote: u8, read: u8 },
+    PecMismatch { addr: u8, cmd: u8, expected: u8, read: u8 },
+    BlockTooLong { addr: u8, cmd: u8, len: u8 },
+    CommandTooWide { field: FieldName, reg: u16 },  // SMBus command codes are a single byte
+    CrcMismatch { addr: u8, cmd: u16, expected: u8, read: u8 },
+    TooManyWords { addr: u8, cmd: u16, len: usize },
+    Bus { kind: BusErrorKind, addr: u8, reg: u16, field: Option<FieldName>, source: I2C::Error },
+}
+
+impl<I2C: i2c::ErrorType> I2CError<I2C> {
+    pub fn bus(addr: u8, reg: u16, source: I2C::Error) -> Self {
+        // Wrap an error from the bus, classified by its embedded-hal error kind
+        I2CError::Bus { kind: source.kind().into(), addr, reg, field: None, source }
+    }
+
+    pub fn field_not_found(field: &str) -> Self {
+        I2CError::FieldNotFound { field: field_name(field) }
+    }
+
+    pub fn with_field(self, field: &str) -> Self {
+        // Attach the name of the field being accessed to a bus error
+        match self {
+            I2CError::Bus { kind, addr, reg, field: None, source } => {
+                I2CError::Bus { kind, addr, reg, field: Some(field_name(field)), source }
+            }
+            other => other,
+        }
+    }
+
+    pub fn bus_kind(&self) -> Option<BusErrorKind> {
+        match self {
+            I2CError::Bus { kind, .. } => Some(*kind),
+            _ => None,
+        }
+    }
+}
+
+impl<I2C: i2c::ErrorType> fmt::Debug for I2CError<I2C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Written out by hand so only the bus error, not the bus itself, needs to be Debug
+        match self {
+            I2CError::FieldNotFound { field } => f.debug_struct("FieldNotFound").field("field", field).finish(),
+            I2CError::BatchFull { field } => f.debug_struct("BatchFull").field("field", field).finish(),
+            I2CError::FieldReadOnly { field } => f.debug_struct("FieldReadOnly").field("field", field).finish(),
+            I2CError::TooManySegments => f.write_str("TooManySegments"),
+            I2CError::TooManyRegisters => f.write_str("TooManyRegisters"),
+            I2CError::VerifyMismatch { reg, wrote, read } => f.debug_struct("VerifyMismatch")
+                .field("reg", reg).field("wrote", wrote).field("read", read).finish(),
+            I2CError::PecMismatch { addr, cmd, expected, read } => f.debug_struct("PecMismatch")
+                .field("addr", addr).field("cmd", cmd).field("expected", expected).field("read", read).finish(),
+            I2CError::BlockTooLong { addr, cmd, len } => f.debug_struct("BlockTooLong")
+                .field("addr", addr).field("cmd", cmd).field("len", len).finish(),
+            I2CError::CommandTooWide { field, reg } => f.debug_struct("CommandTooWide")
+                .field("field", field).field("reg", reg).finish(),
+            I2CError::CrcMismatch { addr, cmd, expected, read } => f.debug_struct("CrcMismatch")
+                .field("addr", addr).field("cmd", cmd).field("expected", expected).field("read", read).finish(),
+            I2CError::TooManyWords { addr, cmd, len } => f.debug_struct("TooManyWords")
+                .field("addr", addr).field("cmd", cmd).field("len", len).finish(),
+            I2CError::Bus { kind, addr, reg, field, source } => f.debug_struct("Bus")
+                .field("kind", kind).field("addr", addr).field("reg", reg).field("field", field).field("source", source).finish(),
+        }
+    }
+}
+
+impl<I2C: i2c::ErrorType> fmt::Display for I2CError<I2C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            I2CError::FieldNotFound { field } => write!(f, "Field not found: {}", field),
+            I2CError::BatchFull { field } => write!(f, "Batch full, could not add field: {}", field),
+            I2CError::FieldReadOnly { field } => write!(f, "Field is read-only: {}", field),
+            I2CError::TooManySegments => write!(f, "Too many read segments, at most {}", crate::chip::MAX_READ_SEGMENTS),
+            I2CError::TooManyRegisters => write!(f, "Field map references too many registers, at most {}", crate::chip_map::MAX_MAP_REGS),
+            I2CError::VerifyMismatch { reg, wrote, read } => {
+                write!(f, "Verify mismatch: 0x{:02X}, wrote 0x{:02X}, read 0x{:02X}", reg, wrote, read)
+            }
+            I2CError::PecMismatch { addr, cmd, expected, read } => {
+                write!(f, "PEC mismatch: device 0x{:02X}, command 0x{:02X}, expected 0x{:02X}, read 0x{:02X}", addr, cmd, expected, read)
+            }
+            I2CError::BlockTooLong { addr, cmd, len } => {
+                write!(f, "Block too long: device 0x{:02X}, command 0x{:02X}, {} bytes", addr, cmd, len)
+            }
+            I2CError::CommandTooWide { field, reg } => {
+                write!(f, "Command too wide for SMBus: {}, register 0x{:04X}", field, reg)
+            }
+            I2CError::CrcMismatch { addr, cmd, expected, read } => {
+                write!(f, "CRC mismatch: device 0x{:02X}, command 0x{:04X}, expected 0x{:02X}, read 0x{:02X}", addr, cmd, expected, read)
+            }
+            I2CError::TooManyWords { addr, cmd, len } => {
+                write!(f, "Too many words: device 0x{:02X}, command 0x{:04X}, {} words, at most {}", addr, cmd, len, crate::command::MAX_COMMAND_WORDS)
+            }
+            I2CError::Bus { kind, addr, reg, field, .. } => {
+                write!(f, "{}: device 0x{:02X}, register 0x{:02X}", kind, addr, reg)?;
+                if let Some(field) = field {
+                    write!(f, ", field {}", field)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+pub fn field_name(field: &str) -> FieldName {
+    // Copy as much of the name as fits, cutting on a char boundary
+    let mut name = FieldName::new();
+    for c in field.chars() {
+        if name.push(c).is_err() {
+            break;
+        }
+    }
+    name
+}
@@ -0,0 +1,157 @@
+// interface.rs
+use embedded_hal::i2c::{self, ErrorKind, I2c, Operation};
+use embedded_hal::spi::{self, SpiDevice};
+use heapless::Vec;
+
+use crate::chip::MAX_READ_SEGMENTS;
+
+// Longest run of registers written in a single SPI transaction
+pub const MAX_SPI_WRITE: usize = 16;
+
+// Buffers filled back to back by one read, bounded so a transport never has to drop any
+pub type ReadSegments<'a> = Vec<&'a mut [u8], MAX_READ_SEGMENTS>;
+
+// Transport that moves register contents to and from a device
+// `reg` is the register address already encoded to the chip's address width and byte order
+// Errors use the embedded-hal I2C error kinds so Chip can classify them the same way for every transport
+pub trait RegisterInterface: i2c::ErrorType {
+    fn read_regs_into(&mut self, addr: u8, reg: &[u8], segments: &mut ReadSegments<'_>) -> Result<(), Self::Error>;
+
+    fn write_regs(&mut self, addr: u8, reg: &[u8], reg_values: &[u8]) -> Result<(), Self::Error>;
+
+    fn read_regs(&mut self, addr: u8, reg: &[u8], reg_values: &mut [u8]) -> Result<(), Self::Error> {
+        // A single segment always fits
+        let mut segments = ReadSegments::new();
+        let _ = segments.push(reg_values);
+        self.read_regs_into(addr, reg, &mut segments)
+    }
+
+    fn read_raw(&mut self, addr: u8, values: &mut [u8]) -> Result<(), Self::Error> {
+        // Plain read without sending a register address first, e.g. the reply to a command
+        self.read_regs(addr, &[], values)
+    }
+
+    fn recover_bus(&mut self) -> bool {
+        // Try to free a stuck bus, returns false if the transport has no way to
+        false
+    }
+}
+
+// Any blocking embedded-hal 1.0 I2C bus, addressed by the chip's I2C address
+impl<T> RegisterInterface for T
+where
+    T: I2c,
+{
+    fn read_regs_into(&mut self, addr: u8, reg: &[u8], segments: &mut ReadSegments<'_>) -> Result<(), Self::Error> {
+        // Register address, then consecutive reads without a restart
+        // Room for the address and every segment, so nothing is dropped
+        let mut operations: Vec<Operation<'_>, { MAX_READ_SEGMENTS + 1 }> = Vec::new();
+        let _ = operations.push(Operation::Write(reg));
+        for segment in segments.iter_mut() {
+            let _ = operations.push(Operation::Read(segment));
+        }
+
+        self.transaction(addr, &mut operations)
+    }
+
+    fn write_regs(&mut self, addr: u8, reg: &[u8], reg_values: &[u8]) -> Result<(), Self::Error> {
+        // Register address followed by the data, no copy needed
+        self.transaction(addr, &mut [Operation::Write(reg), Operation::Write(reg_values)])
+    }
+
+    fn read_regs(&mut self, addr: u8, reg: &[u8], reg_values: &mut [u8]) -> Result<(), Self::Error> {
+        self.transaction(addr, &mut [Operation::Write(reg), Operation::Read(reg_values)])
+    }
+
+    fn read_raw(&mut self, addr: u8, values: &mut [u8]) -> Result<(), Self::Error> {
+        I2c::read(self, addr, values)
+    }
+}
+
+// SPI error reported through the I2C error kinds
+#[derive(Debug)]
+pub enum SpiError<E> {
+    Spi(E),
+    RegAddr { len: usize },  // Register address was not a single byte, e.g. a 16-bit map or command mode
+}
+
+impl<E: spi::Error> i2c::Error for SpiError<E> {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            SpiError::Spi(err) => match err.kind() {
+                spi::ErrorKind::Overrun => ErrorKind::Overrun,
+                spi::ErrorKind::ModeFault | spi::ErrorKind::FrameFormat | spi::ErrorKind::ChipSelectFault => ErrorKind::Bus,
+                _ => ErrorKind::Other,
+            },
+            SpiError::RegAddr { .. } => ErrorKind::Other,
+        }
+    }
+}
+
+// 4-wire SPI device with 7-bit register addresses, MSB set for reads and clear for writes
+// Only single byte register addresses are supported, anything else fails with SpiError::RegAddr
+// The chip's I2C address is ignored, the device is selected by its chip select
+pub struct SpiInterface<SPI> {
+    pub spi: SPI,
+}
+
+impl<SPI> SpiInterface<SPI>
+where
+    SPI: SpiDevice,
+{
+    pub fn new(spi: SPI) -> Self {
+        Self { spi }
+    }
+
+    pub fn release(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<SPI> i2c::ErrorType for SpiInterface<SPI>
+where
+    SPI: SpiDevice,
+{
+    type Error = SpiError<SPI::Error>;
+}
+
+impl<SPI> RegisterInterface for SpiInterface<SPI>
+where
+    SPI: SpiDevice,
+{
+    fn read_regs_into(&mut self, _addr: u8, reg: &[u8], segments: &mut ReadSegments<'_>) -> Result<(), Self::Error> {
+        // Control byte, then the register contents clocked out in one chip select
+        let ctrl_buf = [spi_reg(reg)? | 0x80];
+        let mut operations: Vec<spi::Operation<'_, u8>, { MAX_READ_SEGMENTS + 1 }> = Vec::new();
+        let _ = operations.push(spi::Operation::Write(&ctrl_buf));
+        for segment in segments.iter_mut() {
+            let _ = operations.push(spi::Operation::Read(segment));
+        }
+
+        self.spi.transaction(&mut operations).map_err(SpiError::Spi)
+    }
+
+    fn write_regs(&mut self, _addr: u8, reg: &[u8], reg_values: &[u8]) -> Result<(), Self::Error> {
+        // SPI writes do not auto-increment, every register gets its own control byte
+        let mut reg_addr = spi_reg(reg)?;
+        for chunk in reg_values.chunks(MAX_SPI_WRITE) {
+            let mut buf = [0u8; 2 * MAX_SPI_WRITE];
+            for (pair, reg_value) in buf.chunks_mut(2).zip(chunk.iter()) {
+                pair[0] = reg_addr & 0x7f;
+                pair[1] = *reg_value;
+                reg_addr = reg_addr.wrapping_add(1);
+            }
+            self.spi.write(&buf[..2 * chunk.len()]).map_err(SpiError::Spi)?;
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn spi_reg<E>(reg: &[u8]) -> Result<u8, SpiError<E>> {
+    // SPI register address, anything but a single byte can't be sent in the control byte
+    match reg {
+        [reg] => Ok(*reg),
+        _ => Err(SpiError::RegAddr { len: reg.len() }),
+    }
+}
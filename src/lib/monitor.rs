@@ -0,0 +1,113 @@
+// monitor.rs
+// Hot-plug detection, re-probes the bus on every poll and reports devices coming and going
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+use core::fmt;
+use heapless::Vec;
+use log::{info, warn};
+
+use crate::catalog::{self, Identity};
+use crate::scan::{probe, Probe, ScanError, FIRST_SCAN_ADDR, LAST_SCAN_ADDR, MAX_SCAN_DEVICES};
+
+// Default number of consecutive missed polls before a device counts as removed
+pub const DEFAULT_REMOVE_AFTER: u8 = 3;
+
+#[derive(Copy, Clone)]
+pub enum BusEvent {
+    DeviceAdded(u8, Option<Identity>),
+    DeviceRemoved(u8),
+}
+
+impl BusEvent {
+    pub fn addr(&self) -> u8 {
+        match self {
+            BusEvent::DeviceAdded(addr, _) | BusEvent::DeviceRemoved(addr) => *addr,
+        }
+    }
+}
+
+impl fmt::Display for BusEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BusEvent::DeviceAdded(addr, Some(identity)) => write!(f, "Device added: 0x{:02X}, {}", addr, identity),
+            BusEvent::DeviceAdded(addr, None) => write!(f, "Device added: 0x{:02X}, unknown", addr),
+            BusEvent::DeviceRemoved(addr) => write!(f, "Device removed: 0x{:02X}", addr),
+        }
+    }
+}
+
+// A device currently considered present
+#[derive(Copy, Clone)]
+pub struct PresentDevice {
+    pub addr: u8,
+    pub identity: Option<Identity>,
+    misses: u8,  // Consecutive polls without an acknowledge
+}
+
+pub struct BusMonitor {
+    pub probe: Probe,
+    pub remove_after: u8,
+    devices: Vec<PresentDevice, MAX_SCAN_DEVICES>,
+}
+
+impl BusMonitor {
+    pub fn new(probe: Probe) -> Self {
+        Self { probe, remove_after: DEFAULT_REMOVE_AFTER, devices: Vec::new() }
+    }
+
+    pub fn with_remove_after(mut self, remove_after: u8) -> Self {
+        // Debounce, a device must miss this many polls in a row before it is removed
+        self.remove_after = remove_after.max(1);
+        self
+    }
+
+    pub fn devices(&self) -> &[PresentDevice] {
+        &self.devices
+    }
+
+    pub fn is_present(&self, addr: u8) -> bool {
+        self.devices.iter().any(|device| device.addr == addr)
+    }
+
+    pub fn poll<I2C: I2c, D: DelayNs>(&mut self, i2c: &mut I2C, delay: &mut D) -> Vec<BusEvent, MAX_SCAN_DEVICES> {
+        // Re-probe every address once, call periodically
+        // New devices are identified and reported straight away, missing ones only after remove_after polls
+        // Only a NACK counts as a miss, any other probe error means the bus itself is in trouble,
+        // so the sweep stops there with one warning and the remaining addresses keep their state
+        let mut events = Vec::new();
+        for addr in FIRST_SCAN_ADDR..=LAST_SCAN_ADDR {
+            let acked = match probe(i2c, addr, self.probe) {
+                Ok(acked) => acked,
+                Err(source) => {
+                    warn!("{}, poll stopped", ScanError { addr, source });
+                    break;
+                }
+            };
+            let tracked = self.devices.iter().position(|device| device.addr == addr);
+
+            match (acked, tracked) {
+                (true, Some(idx)) => self.devices[idx].misses = 0,
+                (true, None) => {
+                    let identity = catalog::identify(i2c, addr, delay);
+                    // Capacity covers every scanned address
+                    let _ = self.devices.push(PresentDevice { addr, identity, misses: 0 });
+                    let _ = events.push(BusEvent::DeviceAdded(addr, identity));
+                }
+                (false, Some(idx)) => {
+                    self.devices[idx].misses += 1;
+                    if self.devices[idx].misses >= self.remove_after {
+                        self.devices.remove(idx);
+                        let _ = events.push(BusEvent::DeviceRemoved(addr));
+                    }
+                }
+                (false, None) => {}
+            }
+        }
+
+        for event in events.iter() {
+            info!("{}", event);
+        }
+
+        events
+    }
+}
@@ -0,0 +1,93 @@
+// mux.rs
+// TCA9548A / PCA9548A 8-channel I2C multiplexer
+use embedded_hal::i2c::{self, I2c, Operation};
+use core::cell::RefCell;
+
+pub const MUX_CHANNELS: u8 = 8;
+
+// Default address with A0..A2 tied low, the strapping pins select 0x70..=0x77
+pub const DEFAULT_MUX_ADDR: u8 = 0x70;
+
+struct MuxState<I2C> {
+    i2c: I2C,
+    active: Option<u8>,  // Channel last written to the control register, None if unknown
+}
+
+// Owns the upstream bus and hands out one proxy bus per channel
+pub struct Tca9548a<I2C> {
+    addr: u8,
+    state: RefCell<MuxState<I2C>>,
+}
+
+impl<I2C> Tca9548a<I2C>
+where
+    I2C: I2c,
+{
+    pub fn new(i2c: I2C, addr: u8) -> Self {
+        Self { addr, state: RefCell::new(MuxState { i2c, active: None }) }
+    }
+
+    pub fn channel(&self, channel: u8) -> Option<MuxChannel<'_, I2C>> {
+        // Bus proxy for a downstream channel, the channel is selected on demand
+        (channel < MUX_CHANNELS).then_some(MuxChannel { mux: self, channel })
+    }
+
+    pub fn active_channel(&self) -> Option<u8> {
+        self.state.borrow().active
+    }
+
+    pub fn disconnect(&self) -> Result<(), I2C::Error> {
+        // Open every channel switch, e.g. before talking to devices on the upstream bus
+        let mut state = self.state.borrow_mut();
+        state.active = None;
+        state.i2c.write(self.addr, &[0])
+    }
+
+    pub fn invalidate(&self) {
+        // Forget the active channel, e.g. after the mux was reset
+        self.state.borrow_mut().active = None;
+    }
+
+    pub fn release(self) -> I2C {
+        self.state.into_inner().i2c
+    }
+
+    fn transaction(&self, channel: u8, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), I2C::Error> {
+        let mut state = self.state.borrow_mut();
+        if state.active != Some(channel) {
+            // Unknown state if the select fails, so the next access selects again
+            state.active = None;
+            state.i2c.write(self.addr, &[1 << channel])?;
+            state.active = Some(channel);
+        }
+        state.i2c.transaction(addr, operations)
+    }
+}
+
+// A single downstream channel, behaves like a plain I2C bus
+pub struct MuxChannel<'a, I2C> {
+    mux: &'a Tca9548a<I2C>,
+    channel: u8,
+}
+
+impl<I2C> MuxChannel<'_, I2C> {
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+}
+
+impl<I2C> i2c::ErrorType for MuxChannel<'_, I2C>
+where
+    I2C: I2c,
+{
+    type Error = I2C::Error;
+}
+
+impl<I2C> I2c for MuxChannel<'_, I2C>
+where
+    I2C: I2c,
+{
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        self.mux.transaction(self.channel, address, operations)
+    }
+}
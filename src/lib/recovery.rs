@@ -0,0 +1,97 @@
+// recovery.rs
+// Freeing a bus where a device was reset mid-transfer and is left holding SDA low
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::i2c;
+
+use crate::interface::{ReadSegments, RegisterInterface};
+
+// A stuck device releases SDA within one byte plus the acknowledge bit
+pub const MAX_RECOVERY_CLOCKS: u8 = 9;
+
+// Half of an SCL period at 100 kHz
+pub const RECOVERY_HALF_PERIOD_US: u32 = 5;
+
+// Platform specific part of a bus recovery, e.g. switching the I2C pins to GPIO and back
+pub trait BusRecovery {
+    fn recover(&mut self) -> bool;
+}
+
+pub fn clock_out_bus<SCL, SDA, D>(scl: &mut SCL, sda: &mut SDA, delay: &mut D) -> bool
+where
+    SCL: OutputPin,
+    SDA: OutputPin + InputPin,
+    D: DelayNs,
+{
+    // Clock SCL until the device lets go of SDA, then finish with a STOP
+    // Both pins must be open drain, returns whether SDA was released
+    let _ = sda.set_high();
+    let _ = scl.set_high();
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+
+    for _ in 0..MAX_RECOVERY_CLOCKS {
+        if sda.is_high().unwrap_or(false) {
+            break;
+        }
+        let _ = scl.set_low();
+        delay.delay_us(RECOVERY_HALF_PERIOD_US);
+        let _ = scl.set_high();
+        delay.delay_us(RECOVERY_HALF_PERIOD_US);
+    }
+
+    // STOP: SDA rises while SCL is high
+    let _ = scl.set_low();
+    let _ = sda.set_low();
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+    let _ = scl.set_high();
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+    let _ = sda.set_high();
+    delay.delay_us(RECOVERY_HALF_PERIOD_US);
+
+    sda.is_high().unwrap_or(false)
+}
+
+// Transport paired with a recovery routine that Chip can trigger after repeated bus errors
+pub struct RecoverableBus<I2C, R> {
+    pub i2c: I2C,
+    pub recovery: R,
+}
+
+impl<I2C, R> RecoverableBus<I2C, R> {
+    pub fn new(i2c: I2C, recovery: R) -> Self {
+        Self { i2c, recovery }
+    }
+}
+
+impl<I2C, R> i2c::ErrorType for RecoverableBus<I2C, R>
+where
+    I2C: RegisterInterface,
+{
+    type Error = I2C::Error;
+}
+
+impl<I2C, R> RegisterInterface for RecoverableBus<I2C, R>
+where
+    I2C: RegisterInterface,
+    R: BusRecovery,
+{
+    fn read_regs_into(&mut self, addr: u8, reg: &[u8], segments: &mut ReadSegments<'_>) -> Result<(), Self::Error> {
+        self.i2c.read_regs_into(addr, reg, segments)
+    }
+
+    fn write_regs(&mut self, addr: u8, reg: &[u8], reg_values: &[u8]) -> Result<(), Self::Error> {
+        self.i2c.write_regs(addr, reg, reg_values)
+    }
+
+    fn read_regs(&mut self, addr: u8, reg: &[u8], reg_values: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c.read_regs(addr, reg, reg_values)
+    }
+
+    fn read_raw(&mut self, addr: u8, values: &mut [u8]) -> Result<(), Self::Error> {
+        self.i2c.read_raw(addr, values)
+    }
+
+    fn recover_bus(&mut self) -> bool {
+        self.recovery.recover()
+    }
+}
@@ -0,0 +1,96 @@
+// retry.rs
+use embedded_hal::delay::DelayNs;
+
+use crate::chip::I2CError;
+use crate::error::BusErrorKind;
+use crate::interface::RegisterInterface;
+
+// Which failures are worth another attempt
+#[derive(Copy, Clone, Debug)]
+pub struct RetryOn {
+    pub nack: bool,  // Any NACK, e.g. a sensor busy converting
+    pub arbitration_lost: bool,
+    pub bus: bool,  // Misplaced START/STOP
+    pub other: bool,  // Overrun and anything the HAL reports without a known kind
+    pub verify_mismatch: bool,  // Read-back mismatch left over after write-verify retries
+    pub pec_mismatch: bool,  // SMBus PEC or Sensirion word CRC mismatch, data corrupted on the wire
+}
+
+impl RetryOn {
+    pub const fn nothing() -> Self {
+        Self { nack: false, arbitration_lost: false, bus: false, other: false, verify_mismatch: false, pec_mismatch: false }
+    }
+
+    pub const fn bus_errors() -> Self {
+        // Every error reported by the bus, including corrupted SMBus transfers, but not verify mismatches
+        Self { nack: true, arbitration_lost: true, bus: true, other: true, verify_mismatch: false, pec_mismatch: true }
+    }
+
+    pub fn kind(&self, kind: BusErrorKind) -> bool {
+        match kind {
+            BusErrorKind::NackAddress | BusErrorKind::NackData | BusErrorKind::Nack => self.nack,
+            BusErrorKind::ArbitrationLost => self.arbitration_lost,
+            BusErrorKind::Bus => self.bus,
+            BusErrorKind::Overrun | BusErrorKind::Other => self.other,
+        }
+    }
+}
+
+// How many times a register access is attempted and how long to wait in between
+#[derive(Copy, Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u8,
+    pub delay_us: u32,
+    pub retry_on: RetryOn,
+    pub recover_after: u8,  // Consecutive stuck-bus errors (not NACKs) before a bus recovery, 0 to never recover
+}
+
+impl RetryPolicy {
+    pub const fn none() -> Self {
+        // Single attempt, every error is returned straight away
+        Self { max_attempts: 1, delay_us: 0, retry_on: RetryOn::nothing(), recover_after: 0 }
+    }
+
+    pub const fn bus_errors(max_attempts: u8, delay_us: u32) -> Self {
+        // Retry anything the bus reports, e.g. a NACK from a busy sensor
+        Self { max_attempts, delay_us, retry_on: RetryOn::bus_errors(), recover_after: 0 }
+    }
+
+    pub const fn with_recover_after(mut self, recover_after: u8) -> Self {
+        // Recover the bus after this many bus, arbitration or other errors in a row, NACKs reset the count
+        // Needs a transport that supports it
+        self.recover_after = recover_after;
+        self
+    }
+
+    pub fn should_retry<I2C: RegisterInterface>(&self, err: &I2CError<I2C>) -> bool {
+        match err {
+            I2CError::Bus { kind, .. } => self.retry_on.kind(*kind),
+            I2CError::VerifyMismatch { .. } => self.retry_on.verify_mismatch,
+            I2CError::PecMismatch { .. } | I2CError::CrcMismatch { .. } => self.retry_on.pec_mismatch,
+            _ => false,
+        }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+// Running totals kept by a Chip
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RetryStats {
+    pub retries: u32,  // Extra attempts made after a retryable error
+    pub failures: u32,  // Accesses that still failed once the policy gave up
+    pub recoveries: u32,  // Bus recoveries triggered by recover_after
+    pub bus_errors_in_row: u8,  // Stuck-bus errors since the last successful access, NACK or recovery
+}
+
+// Delay for chips without retries, or where back-to-back retries are fine
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    fn delay_ns(&mut self, _ns: u32) {}
+}
@@ -0,0 +1,133 @@
+// sample.rs
+// Sinks that consume typed measurement records, the records themselves live in telemetry_frame
+use core::fmt::Write;
+
+use heapless::{Deque, Vec};
+use rtt_target::{ChannelMode, UpChannel};
+
+use crate::scheduler::{JobId, JobSink};
+
+pub use telemetry_frame::{Channel, Sample, Status, Unit, SAMPLE_LEN};
+
+pub trait SampleSink {
+    fn write(&mut self, sample: &Sample);
+
+    fn write_all(&mut self, samples: &[Sample]) {
+        for sample in samples {
+            self.write(sample);
+        }
+    }
+}
+
+// Scheduler jobs returning samples can deliver straight into any sample sink
+impl<S: SampleSink, const N: usize> JobSink<Vec<Sample, N>> for S {
+    fn deliver(&mut self, _job: JobId, _timestamp_us: u64, output: Vec<Sample, N>) {
+        self.write_all(&output);
+    }
+}
+
+// One text line per sample, on anything that implements fmt::Write
+pub struct TextSink<W> {
+    pub writer: W,
+}
+
+impl<W: Write> TextSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> SampleSink for TextSink<W> {
+    fn write(&mut self, sample: &Sample) {
+        // Output is best effort, a full or disconnected link drops the line
+        let _ = write!(self.writer, "{}\r\n", sample);
+    }
+}
+
+// RTT up channel in text mode, e.g. the print channel from rtt_init!
+pub type RttTextSink = TextSink<UpChannel>;
+
+// Serial port transmitter, e.g. stm32h7xx_hal::serial::Tx
+pub type UartSink<TX> = TextSink<TX>;
+
+// Raw SAMPLE_LEN byte records on a dedicated RTT up channel
+// There is no framing, the host stays aligned only because records are written whole or not at all
+pub struct RttBinarySink {
+    channel: UpChannel,
+    pub dropped: u32,
+}
+
+impl RttBinarySink {
+    pub fn new(mut channel: UpChannel) -> Self {
+        // Trim mode writes whatever fits, a truncated record would misalign every later one
+        if channel.mode() == ChannelMode::NoBlockTrim {
+            channel.set_mode(ChannelMode::NoBlockSkip);
+        }
+        Self { channel, dropped: 0 }
+    }
+
+    pub fn release(self) -> UpChannel {
+        self.channel
+    }
+}
+
+impl SampleSink for RttBinarySink {
+    fn write(&mut self, sample: &Sample) {
+        // Skip mode drops the whole record when the buffer is full, count it so the host knows
+        if self.channel.write(&sample.to_bytes()) < SAMPLE_LEN {
+            self.dropped += 1;
+        }
+    }
+}
+
+// Keeps the latest N samples in memory, oldest are overwritten
+pub struct RingBufferSink<const N: usize> {
+    samples: Deque<Sample, N>,
+    pub overwritten: u32,
+}
+
+impl<const N: usize> RingBufferSink<N> {
+    pub fn new() -> Self {
+        Self { samples: Deque::new(), overwritten: 0 }
+    }
+
+    pub fn pop(&mut self) -> Option<Sample> {
+        self.samples.pop_front()
+    }
+
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+impl<const N: usize> SampleSink for RingBufferSink<N> {
+    fn write(&mut self, sample: &Sample) {
+        if self.samples.is_full() {
+            self.samples.pop_front();
+            self.overwritten += 1;
+        }
+        let _ = self.samples.push_back(*sample);
+    }
+}
+
+impl<const N: usize> Default for RingBufferSink<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
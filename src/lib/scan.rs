@@ -0,0 +1,106 @@
+// scan.rs
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{Error as _, ErrorKind, I2c};
+use core::fmt;
+use heapless::Vec;
+
+use crate::catalog::{self, Identity};
+use crate::error::BusErrorKind;
+
+// Lowest and highest 7-bit addresses that are not reserved by the I2C specification
+pub const FIRST_SCAN_ADDR: u8 = 0x08;
+pub const LAST_SCAN_ADDR: u8 = 0x77;
+
+// Every non-reserved address can respond
+pub const MAX_SCAN_DEVICES: usize = (LAST_SCAN_ADDR - FIRST_SCAN_ADDR + 1) as usize;
+
+// How each address is probed, none of them write any data to the device
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Probe {
+    ZeroLengthWrite,  // SMBus Quick Write, not supported by every HAL
+    Read,  // Read a single byte
+    Auto,  // Read for EEPROM and other ranges where a Quick Write can latch state, otherwise Quick Write
+}
+
+impl Probe {
+    pub fn for_addr(self, addr: u8) -> Probe {
+        // Resolve Auto to the probe used for an address, following i2cdetect
+        match self {
+            Probe::Auto if (0x30..=0x37).contains(&addr) || (0x50..=0x5f).contains(&addr) => Probe::Read,
+            Probe::Auto => Probe::ZeroLengthWrite,
+            probe => probe,
+        }
+    }
+}
+
+pub fn is_reserved(addr: u8) -> bool {
+    // General call, CBUS, high-speed master codes, 10-bit addressing and future use
+    !(FIRST_SCAN_ADDR..=LAST_SCAN_ADDR).contains(&addr)
+}
+
+// A probe that failed for any reason other than a NACK, e.g. a stuck bus or a probe the HAL can't issue
+#[derive(Debug)]
+pub struct ScanError<E> {
+    pub addr: u8,
+    pub source: E,
+}
+
+impl<E: embedded_hal::i2c::Error> fmt::Display for ScanError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Probe failed: device 0x{:02X}, {}", self.addr, BusErrorKind::from(self.source.kind()))
+    }
+}
+
+pub fn probe<I2C: I2c>(i2c: &mut I2C, addr: u8, probe: Probe) -> Result<bool, I2C::Error> {
+    // Check whether a device acknowledges its address, only a NACK means nothing is there
+    let result = match probe.for_addr(addr) {
+        Probe::ZeroLengthWrite => i2c.write(addr, &[]),
+        _ => i2c.read(addr, &mut [0u8]),
+    };
+    match result {
+        Ok(()) => Ok(true),
+        Err(err) if matches!(err.kind(), ErrorKind::NoAcknowledge(_)) => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+pub fn scan<I2C: I2c>(i2c: &mut I2C, probe_with: Probe) -> Result<Vec<u8, MAX_SCAN_DEVICES>, ScanError<I2C::Error>> {
+    // Probe every non-reserved address, returns the ones that acknowledged in ascending order
+    let mut found = Vec::new();
+    for addr in FIRST_SCAN_ADDR..=LAST_SCAN_ADDR {
+        if probe(i2c, addr, probe_with).map_err(|source| ScanError { addr, source })? {
+            // Capacity covers every scanned address
+            let _ = found.push(addr);
+        }
+    }
+    Ok(found)
+}
+
+// A responding address, along with what it was identified as
+#[derive(Copy, Clone)]
+pub struct ScanResult {
+    pub addr: u8,
+    pub identity: Option<Identity>,
+}
+
+impl fmt::Display for ScanResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.identity {
+            Some(identity) => write!(f, "0x{:02X}: {}", self.addr, identity),
+            None => write!(f, "0x{:02X}: unknown", self.addr),
+        }
+    }
+}
+
+pub fn scan_identify<I2C: I2c, D: DelayNs>(
+    i2c: &mut I2C,
+    probe_with: Probe,
+    delay: &mut D,
+) -> Result<Vec<ScanResult, MAX_SCAN_DEVICES>, ScanError<I2C::Error>> {
+    // Scan, then match each responding address against the known-device catalog
+    let mut results = Vec::new();
+    for addr in scan(i2c, probe_with)? {
+        let _ = results.push(ScanResult { addr, identity: catalog::identify(i2c, addr, delay) });
+    }
+    Ok(results)
+}
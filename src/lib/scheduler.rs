@@ -0,0 +1,160 @@
+// scheduler.rs
+// Cooperative scheduler for periodic sampling jobs, no RTOS needed
+use embedded_hal::delay::DelayNs;
+use cortex_m::peripheral::{DCB, DWT};
+use heapless::Vec;
+use log::warn;
+
+pub type JobId = u8;
+
+// Longest single sleep in run, well below a DwtClock wrap (~8 s at 520 MHz) so the clock is read often enough
+pub const MAX_SLEEP_US: u32 = 1_000_000;
+
+// Free running microsecond clock
+pub trait Monotonic {
+    fn now_us(&mut self) -> u64;
+}
+
+// Cortex-M cycle counter extended to 64 bits, must be read at least once per counter wrap (~42 s at 100 MHz)
+pub struct DwtClock {
+    cycles_per_us: u32,
+    last: u32,
+    high: u64,
+}
+
+impl DwtClock {
+    pub fn new(dcb: &mut DCB, dwt: &mut DWT, sysclk_hz: u32) -> Self {
+        dcb.enable_trace();
+        dwt.enable_cycle_counter();
+        Self { cycles_per_us: (sysclk_hz / 1_000_000).max(1), last: DWT::cycle_count(), high: 0 }
+    }
+}
+
+impl Monotonic for DwtClock {
+    fn now_us(&mut self) -> u64 {
+        let cycles = DWT::cycle_count();
+        if cycles < self.last {
+            self.high += 1 << 32;
+        }
+        self.last = cycles;
+        (self.high | cycles as u64) / self.cycles_per_us as u64
+    }
+}
+
+// Receives the output of every job run
+pub trait JobSink<T> {
+    fn deliver(&mut self, job: JobId, timestamp_us: u64, output: T);
+}
+
+// Timing statistics of a single job
+#[derive(Copy, Clone, Debug, Default)]
+pub struct JobStats {
+    pub runs: u32,
+    pub overruns: u32,  // Runs that started a full period or more late, the missed runs are skipped
+    pub last_jitter_us: u32,  // How late the last run started
+    pub max_jitter_us: u32,
+    pub max_duration_us: u32,
+}
+
+pub struct Job {
+    pub id: JobId,
+    pub period_us: u64,
+    pub stats: JobStats,
+    next_due_us: u64,
+}
+
+impl Job {
+    pub fn next_due_us(&self) -> u64 {
+        self.next_due_us
+    }
+}
+
+pub struct Scheduler<const N: usize> {
+    jobs: Vec<Job, N>,
+}
+
+impl<const N: usize> Scheduler<N> {
+    pub fn new() -> Self {
+        Self { jobs: Vec::new() }
+    }
+
+    pub fn add(&mut self, id: JobId, period_us: u64, now_us: u64) -> Result<(), JobId> {
+        // Register a job, first run is due straight away, returns the ID back if the scheduler is full
+        let job = Job { id, period_us: period_us.max(1), stats: JobStats::default(), next_due_us: now_us };
+        self.jobs.push(job).map_err(|job| job.id)
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    pub fn stats(&self, id: JobId) -> Option<JobStats> {
+        self.jobs.iter().find(|job| job.id == id).map(|job| job.stats)
+    }
+
+    pub fn next_due_us(&self) -> Option<u64> {
+        self.jobs.iter().map(|job| job.next_due_us).min()
+    }
+
+    pub fn poll<C, T, S>(&mut self, clock: &mut C, mut run: impl FnMut(JobId) -> T, sink: &mut S) -> usize
+    where
+        C: Monotonic,
+        S: JobSink<T>,
+    {
+        // Run every job that is due, in registration order, returns how many ran
+        let mut ran = 0;
+        for job in self.jobs.iter_mut() {
+            let start_us = clock.now_us();
+            if start_us < job.next_due_us {
+                continue;
+            }
+
+            let late_us = start_us - job.next_due_us;
+            let output = run(job.id);
+            let end_us = clock.now_us();
+            sink.deliver(job.id, start_us, output);
+            ran += 1;
+
+            let stats = &mut job.stats;
+            stats.runs += 1;
+            stats.last_jitter_us = late_us.min(u32::MAX as u64) as u32;
+            stats.max_jitter_us = stats.max_jitter_us.max(stats.last_jitter_us);
+            stats.max_duration_us = stats.max_duration_us.max((end_us - start_us).min(u32::MAX as u64) as u32);
+
+            // Keep to the original grid, skipping any slots that were missed
+            let missed = late_us / job.period_us;
+            if missed > 0 {
+                stats.overruns += 1;
+                warn!("Job Overrun: {}, {} us late, {} run(s) skipped", job.id, late_us, missed);
+            }
+            job.next_due_us += (missed + 1) * job.period_us;
+        }
+
+        ran
+    }
+
+    pub fn run<C, D, T, S>(&mut self, clock: &mut C, delay: &mut D, mut run: impl FnMut(JobId) -> T, sink: &mut S) -> !
+    where
+        C: Monotonic,
+        D: DelayNs,
+        S: JobSink<T>,
+    {
+        // Poll forever, sleeping until the next job is due, at most MAX_SLEEP_US at a time
+        loop {
+            self.poll(clock, &mut run, sink);
+
+            let now_us = clock.now_us();
+            if let Some(next_due_us) = self.next_due_us() {
+                if next_due_us > now_us {
+                    delay.delay_us((next_due_us - now_us).min(MAX_SLEEP_US as u64) as u32);
+                }
+            }
+        }
+    }
+}
+
+impl<const N: usize> Default for Scheduler<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
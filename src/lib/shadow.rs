@@ -0,0 +1,39 @@
+// shadow.rs
+use heapless::FnvIndexMap;
+
+// Number of registers a shadow cache can hold, must be a power of two
+pub const SHADOW_CACHE_REGS: usize = 64;
+
+// Write-through copy of the last known value of non-volatile registers
+pub struct ShadowCache {
+    regs: FnvIndexMap<u16, u8, SHADOW_CACHE_REGS>,
+}
+
+impl ShadowCache {
+    pub fn new() -> Self {
+        Self { regs: FnvIndexMap::new() }
+    }
+
+    pub fn get(&self, reg: u16) -> Option<u8> {
+        self.regs.get(&reg).copied()
+    }
+
+    pub fn update(&mut self, reg: u16, reg_val: u8) {
+        // A full cache simply stops shadowing new registers
+        let _ = self.regs.insert(reg, reg_val);
+    }
+
+    pub fn invalidate_reg(&mut self, reg: u16) {
+        self.regs.remove(&reg);
+    }
+
+    pub fn invalidate(&mut self) {
+        self.regs.clear();
+    }
+}
+
+impl Default for ShadowCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
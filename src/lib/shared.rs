@@ -0,0 +1,149 @@
+// shared.rs
+// Sharing one bus between several chips, pick the flavour that fits the context the chips run in
+use embedded_hal::i2c::{self, I2c};
+use core::cell::RefCell;
+use critical_section::Mutex;
+
+use crate::chip::Chip;
+use crate::chip_map::FieldMapProvider;
+use crate::interface::{ReadSegments, RegisterInterface};
+use crate::recovery::{BusRecovery, RecoverableBus};
+
+pub use embedded_hal_bus::i2c::{AtomicDevice, AtomicError, CriticalSectionDevice, RefCellDevice};
+pub use embedded_hal_bus::util::AtomicCell;
+
+// Every transaction runs in a critical section, safe to share with interrupt handlers
+pub type CriticalSectionBus<I2C> = Mutex<RefCell<I2C>>;
+
+// No locking, a transaction that finds the bus in use fails with AtomicError::Busy
+// Suits RTIC, where tasks sharing the bus as a resource never preempt each other mid-transaction
+pub type AtomicBus<I2C> = AtomicCell<I2C>;
+
+// Chips used from a single context only, e.g. everything in main
+pub type RefCellBus<I2C> = RefCell<I2C>;
+
+pub fn critical_section_bus<I2C: I2c>(i2c: I2C) -> CriticalSectionBus<I2C> {
+    Mutex::new(RefCell::new(i2c))
+}
+
+pub fn atomic_bus<I2C: I2c>(i2c: I2C) -> AtomicBus<I2C> {
+    AtomicCell::new(i2c)
+}
+
+// Critical section bus that can also be recovered, recovery holds the same lock as a transaction
+// so no other chip on the bus starts a transfer while the pins are GPIO or the peripheral is disabled
+pub type RecoverableCriticalSectionBus<I2C, R> = CriticalSectionBus<RecoverableBus<I2C, R>>;
+
+pub fn recoverable_critical_section_bus<I2C: I2c, R: BusRecovery>(i2c: I2C, recovery: R) -> RecoverableCriticalSectionBus<I2C, R> {
+    Mutex::new(RefCell::new(RecoverableBus::new(i2c, recovery)))
+}
+
+// One chip's handle to a RecoverableCriticalSectionBus
+pub struct RecoverableDevice<'a, I2C, R> {
+    bus: &'a RecoverableCriticalSectionBus<I2C, R>,
+}
+
+impl<'a, I2C, R> RecoverableDevice<'a, I2C, R> {
+    pub fn new(bus: &'a RecoverableCriticalSectionBus<I2C, R>) -> Self {
+        Self { bus }
+    }
+}
+
+impl<I2C, R> i2c::ErrorType for RecoverableDevice<'_, I2C, R>
+where
+    I2C: I2c,
+{
+    type Error = I2C::Error;
+}
+
+impl<I2C, R> RegisterInterface for RecoverableDevice<'_, I2C, R>
+where
+    I2C: I2c,
+    R: BusRecovery,
+{
+    fn read_regs_into(&mut self, addr: u8, reg: &[u8], segments: &mut ReadSegments<'_>) -> Result<(), Self::Error> {
+        critical_section::with(|cs| self.bus.borrow_ref_mut(cs).read_regs_into(addr, reg, segments))
+    }
+
+    fn write_regs(&mut self, addr: u8, reg: &[u8], reg_values: &[u8]) -> Result<(), Self::Error> {
+        critical_section::with(|cs| self.bus.borrow_ref_mut(cs).write_regs(addr, reg, reg_values))
+    }
+
+    fn read_raw(&mut self, addr: u8, values: &mut [u8]) -> Result<(), Self::Error> {
+        critical_section::with(|cs| self.bus.borrow_ref_mut(cs).read_raw(addr, values))
+    }
+
+    fn recover_bus(&mut self) -> bool {
+        critical_section::with(|cs| self.bus.borrow_ref_mut(cs).recover_bus())
+    }
+}
+
+impl<'a, I2C, MAP> Chip<CriticalSectionDevice<'a, I2C>, MAP>
+where
+    I2C: I2c,
+    MAP: FieldMapProvider,
+{
+    pub fn new_critical_section(bus: &'a CriticalSectionBus<I2C>, addr: u8) -> Self {
+        Self::new(CriticalSectionDevice::new(bus), addr)
+    }
+}
+
+impl<'a, I2C, MAP> Chip<AtomicDevice<'a, I2C>, MAP>
+where
+    I2C: I2c,
+    MAP: FieldMapProvider,
+{
+    pub fn new_atomic(bus: &'a AtomicBus<I2C>, addr: u8) -> Self {
+        Self::new(AtomicDevice::new(bus), addr)
+    }
+}
+
+impl<'a, I2C, MAP> Chip<RefCellDevice<'a, I2C>, MAP>
+where
+    I2C: I2c,
+    MAP: FieldMapProvider,
+{
+    pub fn new_ref_cell(bus: &'a RefCellBus<I2C>, addr: u8) -> Self {
+        Self::new(RefCellDevice::new(bus), addr)
+    }
+}
+
+impl<'a, I2C, R, MAP> Chip<RecoverableDevice<'a, I2C, R>, MAP>
+where
+    I2C: I2c,
+    R: BusRecovery,
+    MAP: FieldMapProvider,
+{
+    pub fn new_recoverable(bus: &'a RecoverableCriticalSectionBus<I2C, R>, addr: u8) -> Self {
+        Self::new(RecoverableDevice::new(bus), addr)
+    }
+}
+
+#[cfg(feature = "embassy")]
+pub use self::embassy::*;
+
+#[cfg(feature = "embassy")]
+mod embassy {
+    use embassy_sync::blocking_mutex::raw::RawMutex;
+    use embassy_sync::mutex::Mutex;
+    use embedded_hal_async::i2c::I2c;
+
+    use crate::chip_async::AsyncChip;
+    use crate::chip_map::FieldMapProvider;
+
+    pub use embassy_embedded_hal::shared_bus::asynch::i2c::I2cDevice as AsyncMutexDevice;
+
+    // Async mutex for embassy tasks, a task waiting for the bus yields instead of blocking
+    pub type AsyncMutexBus<M, I2C> = Mutex<M, I2C>;
+
+    impl<'a, M, I2C, MAP> AsyncChip<AsyncMutexDevice<'a, M, I2C>, MAP>
+    where
+        M: RawMutex,
+        I2C: I2c,
+        MAP: FieldMapProvider,
+    {
+        pub fn new_async_mutex(bus: &'a AsyncMutexBus<M, I2C>, addr: u8) -> Self {
+            Self::new(AsyncMutexDevice::new(bus), addr)
+        }
+    }
+}
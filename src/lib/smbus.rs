@@ -0,0 +1,215 @@
+// smbus.rs
+// SMBus commands on top of Chip, command codes are the register addresses in the field map
+use embedded_hal::delay::DelayNs;
+use log::{self, info};
+
+use crate::chip::{Chip, I2CError};
+use crate::chip_map::FieldMapProvider;
+use crate::error::field_name;
+use crate::interface::RegisterInterface;
+
+// Longest SMBus block, excluding the count byte
+pub const MAX_BLOCK_LEN: usize = 32;
+
+pub fn pec(bytes: &[u8]) -> u8 {
+    // SMBus Packet Error Code, CRC-8 with polynomial x^8 + x^2 + x + 1 and no reflection
+    crc8(0x07, 0x00, bytes)
+}
+
+pub fn crc8(poly: u8, init: u8, bytes: &[u8]) -> u8 {
+    let mut crc = init;
+    for byte in bytes.iter() {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ poly } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+impl<I2C, MAP, DELAY> Chip<I2C, MAP, DELAY>
+where
+    I2C: RegisterInterface,
+    MAP: FieldMapProvider,
+    DELAY: DelayNs,
+{
+    pub fn set_pec(&mut self, pec: bool) {
+        // Append a PEC byte to every SMBus write and check it on every SMBus read
+        self.pec = pec;
+    }
+
+    pub fn read_byte_data(&mut self, cmd: u8) -> Result<u8, I2CError<I2C>> {
+        let mut data = [0u8; 1];
+        self.smbus_read(cmd, &[], &mut data)?;
+        info!("SMBus Read Byte: 0x{:.02X}, 0x{:.02X}", cmd, data[0]);
+
+        Ok(data[0])
+    }
+
+    pub fn write_byte_data(&mut self, cmd: u8, value: u8) -> Result<(), I2CError<I2C>> {
+        self.smbus_write(cmd, &[value])?;
+        info!("SMBus Write Byte: 0x{:.02X}, 0x{:.02X}", cmd, value);
+
+        Ok(())
+    }
+
+    pub fn read_word_data(&mut self, cmd: u8) -> Result<u16, I2CError<I2C>> {
+        // Words are sent low byte first
+        let mut data = [0u8; 2];
+        self.smbus_read(cmd, &[], &mut data)?;
+        let word = u16::from_le_bytes(data);
+        info!("SMBus Read Word: 0x{:.02X}, 0x{:.04X}", cmd, word);
+
+        Ok(word)
+    }
+
+    pub fn write_word_data(&mut self, cmd: u8, word: u16) -> Result<(), I2CError<I2C>> {
+        self.smbus_write(cmd, &word.to_le_bytes())?;
+        info!("SMBus Write Word: 0x{:.02X}, 0x{:.04X}", cmd, word);
+
+        Ok(())
+    }
+
+    pub fn process_call(&mut self, cmd: u8, word: u16) -> Result<u16, I2CError<I2C>> {
+        // Write a word and read the reply word in one transaction, with a repeated start
+        let mut data = [0u8; 2];
+        self.smbus_read(cmd, &word.to_le_bytes(), &mut data)?;
+        let reply = u16::from_le_bytes(data);
+        info!("SMBus Process Call: 0x{:.02X}, 0x{:.04X} -> 0x{:.04X}", cmd, word, reply);
+
+        Ok(reply)
+    }
+
+    pub fn block_read(&mut self, cmd: u8, block: &mut [u8]) -> Result<usize, I2CError<I2C>> {
+        // Read a count byte followed by that many data bytes, returns the count
+        // Transfer lengths are fixed up front, so the count is fetched first and the block is then
+        // read again with exactly count bytes, so the PEC is taken from where the device sends it
+        let addr = self.i2c_addr;
+        let max_len = block.len().min(MAX_BLOCK_LEN);
+        let mut count = [0u8];
+        self.retrying(|this| {
+            this.i2c.read_regs(addr, &[cmd], &mut count)
+                .map_err(|source| I2CError::bus(addr, cmd as u16, source))
+        })?;
+        if count[0] as usize > max_len {
+            return Err(I2CError::BlockTooLong { addr, cmd, len: count[0] });
+        }
+
+        let count = count[0] as usize;
+        let mut data = [0u8; MAX_BLOCK_LEN + 1];
+        self.smbus_read(cmd, &[], &mut data[..count + 1])?;
+
+        // A count that changed between the two reads fails the PEC, without PEC a shorter block is taken as is
+        let len = data[0] as usize;
+        if len > count {
+            return Err(I2CError::BlockTooLong { addr, cmd, len: data[0] });
+        }
+        block[..len].copy_from_slice(&data[1..len + 1]);
+        info!("SMBus Block Read: 0x{:.02X}, {} bytes", cmd, len);
+
+        Ok(len)
+    }
+
+    pub fn block_write(&mut self, cmd: u8, block: &[u8]) -> Result<(), I2CError<I2C>> {
+        // Write a count byte followed by the data
+        if block.len() > MAX_BLOCK_LEN {
+            return Err(I2CError::BlockTooLong { addr: self.i2c_addr, cmd, len: block.len() as u8 });
+        }
+        let mut data = [0u8; MAX_BLOCK_LEN + 1];
+        data[0] = block.len() as u8;
+        data[1..block.len() + 1].copy_from_slice(block);
+        self.smbus_write(cmd, &data[..block.len() + 1])?;
+        info!("SMBus Block Write: 0x{:.02X}, {} bytes", cmd, block.len());
+
+        Ok(())
+    }
+
+    pub fn read_word_str(&mut self, reg_str: &str) -> Result<u16, I2CError<I2C>> {
+        // Read Word by register name
+        let cmd = Self::smbus_cmd(reg_str)?;
+        self.read_word_data(cmd).map_err(|err| err.with_field(reg_str))
+    }
+
+    pub fn write_word_str(&mut self, reg_str: &str, word: u16) -> Result<(), I2CError<I2C>> {
+        // Write Word by register name
+        let cmd = Self::smbus_cmd(reg_str)?;
+        self.write_word_data(cmd, word).map_err(|err| err.with_field(reg_str))
+    }
+
+    fn smbus_cmd(reg_str: &str) -> Result<u8, I2CError<I2C>> {
+        let field = MAP::get_field(reg_str).ok_or_else(|| I2CError::field_not_found(reg_str))?;
+        u8::try_from(field.reg).map_err(|_| I2CError::CommandTooWide { field: field_name(reg_str), reg: field.reg })
+    }
+
+    fn smbus_read(&mut self, cmd: u8, args: &[u8], data: &mut [u8]) -> Result<(), I2CError<I2C>> {
+        // Command code and any arguments, then a repeated start and `data.len()` bytes, plus PEC if enabled
+        let addr = self.i2c_addr;
+        let mut header = [0u8; 3];
+        header[0] = cmd;
+        header[1..args.len() + 1].copy_from_slice(args);
+        let header = &header[..args.len() + 1];
+
+        let mut buf = [0u8; MAX_BLOCK_LEN + 2];
+        let read_len = data.len() + self.pec as usize;
+        self.retrying(|this| {
+            this.i2c.read_regs(addr, header, &mut buf[..read_len])
+                .map_err(|source| I2CError::bus(addr, cmd as u16, source))?;
+
+            if this.pec {
+                // PEC covers both address bytes, the command and everything after it
+                let mut crc = crc8(0x07, pec(&[addr << 1]), header);
+                crc = crc8(0x07, crc, &[(addr << 1) | 1]);
+                crc = crc8(0x07, crc, &buf[..data.len()]);
+                if crc != buf[data.len()] {
+                    return Err(I2CError::PecMismatch { addr, cmd, expected: crc, read: buf[data.len()] });
+                }
+            }
+
+            Ok(())
+        })?;
+        data.copy_from_slice(&buf[..data.len()]);
+
+        Ok(())
+    }
+
+    fn smbus_write(&mut self, cmd: u8, data: &[u8]) -> Result<(), I2CError<I2C>> {
+        // Command code followed by the data, plus PEC if enabled
+        let addr = self.i2c_addr;
+        let mut buf = [0u8; MAX_BLOCK_LEN + 2];
+        buf[..data.len()].copy_from_slice(data);
+        let mut write_len = data.len();
+        if self.pec {
+            buf[write_len] = crc8(0x07, pec(&[addr << 1, cmd]), data);
+            write_len += 1;
+        }
+
+        self.retrying(|this| {
+            this.i2c.write_regs(addr, &[cmd], &buf[..write_len])
+                .map_err(|source| I2CError::bus(addr, cmd as u16, source))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::word_crc;
+
+    #[test]
+    fn crc8_check_value() {
+        // CRC-8/SMBUS check value over "123456789"
+        assert_eq!(crc8(0x07, 0x00, b"123456789"), 0xf4);
+        assert_eq!(pec(b"123456789"), 0xf4);
+    }
+
+    #[test]
+    fn crc8_of_nothing_is_the_initial_value() {
+        assert_eq!(crc8(0x31, 0xff, &[]), 0xff);
+    }
+
+    #[test]
+    fn word_crc_matches_the_sensirion_example() {
+        // Example from the Sensirion datasheets
+        assert_eq!(word_crc(0xbeef), 0x92);
+    }
+}
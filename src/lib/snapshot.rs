@@ -0,0 +1,339 @@
+// snapshot.rs
+use embedded_hal::delay::DelayNs;
+use core::fmt;
+use core::marker::PhantomData;
+use heapless::Vec;
+use log::warn;
+
+use crate::chip::{Chip, I2CError};
+use crate::interface::RegisterInterface;
+use crate::chip_map::{Field, FieldMapProvider, FieldValue, MAX_MAP_REGS};
+
+// Longest run of contiguous registers fetched in a single burst read
+pub const MAX_BURST_LEN: usize = 32;
+
+// Raw value of a single register captured in a snapshot
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub struct RegValue {
+    pub reg: u16,
+    pub value: u8,
+}
+
+// Values of every register referenced by a field map, sorted by address
+pub struct RegisterSnapshot<MAP> {
+    pub regs: Vec<RegValue, MAX_MAP_REGS>,
+    _map: PhantomData<MAP>,
+}
+
+impl<MAP> RegisterSnapshot<MAP>
+where
+    MAP: FieldMapProvider,
+{
+    pub fn new() -> Self {
+        Self { regs: Vec::new(), _map: PhantomData }
+    }
+
+    pub fn get(&self, reg: u16) -> Option<u8> {
+        // Look up the captured value of a register
+        self.regs.iter().find(|reg_val| reg_val.reg == reg).map(|reg_val| reg_val.value)
+    }
+
+    pub fn get_field(&self, field: &str) -> Option<u8> {
+        // Look up the captured value of a field by name
+        let field_dets = MAP::get_field(field)?;
+        self.get(field_dets.reg).map(|reg_val| field_dets.extract(reg_val))
+    }
+
+    pub fn diff(&self, other: &Self) -> SnapshotDiff<MAP> {
+        // Compare against another snapshot, treating self as the old state
+        // Registers only present in one of the snapshots are ignored
+        let mut diff = SnapshotDiff::new();
+        for reg_val in self.regs.iter() {
+            if let Some(new) = other.get(reg_val.reg) {
+                if new != reg_val.value {
+                    let _ = diff.changes.push(RegisterChange { reg: reg_val.reg, old: reg_val.value, new, _map: PhantomData });
+                }
+            }
+        }
+        diff
+    }
+}
+
+impl<MAP> Default for RegisterSnapshot<MAP>
+where
+    MAP: FieldMapProvider,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<MAP> Clone for RegisterSnapshot<MAP> {
+    fn clone(&self) -> Self {
+        Self { regs: self.regs.clone(), _map: PhantomData }
+    }
+}
+
+impl<MAP> fmt::Display for RegisterSnapshot<MAP>
+where
+    MAP: FieldMapProvider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // One line per register, followed by its decoded fields from MSB to LSB
+        for reg_val in self.regs.iter() {
+            writeln!(f, "0x{:02X}: {:08b}, 0x{:02X}, {}", reg_val.reg, reg_val.value, reg_val.value, reg_val.value)?;
+
+            let mut fields: Vec<FieldValue, 16> = Vec::new();
+            for field_val in MAP::decode(reg_val.reg, reg_val.value) {
+                let _ = fields.push(field_val);
+            }
+            fields.sort_unstable_by(|a, b| (b.field.offset, b.field.bits).cmp(&(a.field.offset, a.field.bits)));
+
+            for field_val in fields.iter() {
+                writeln!(
+                    f,
+                    "    {}: {:0width$b}, 0x{:02X}, {}",
+                    field_val.name, field_val.value, field_val.value, field_val.value, width = field_val.field.bits as usize,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// A single field whose value differs between two snapshots
+pub struct FieldChange {
+    pub name: &'static str,
+    pub field: &'static Field,
+    pub old: u8,
+    pub new: u8,
+}
+
+// A register whose value differs between two snapshots
+pub struct RegisterChange<MAP> {
+    pub reg: u16,
+    pub old: u8,
+    pub new: u8,
+    _map: PhantomData<MAP>,
+}
+
+impl<MAP> RegisterChange<MAP>
+where
+    MAP: FieldMapProvider,
+{
+    pub fn field_changes(&self) -> impl Iterator<Item = FieldChange> {
+        // Named fields within the register whose value changed
+        let (old, new) = (self.old, self.new);
+        MAP::fields_in_reg(self.reg)
+            .map(move |(name, field)| FieldChange { name, field, old: field.extract(old), new: field.extract(new) })
+            .filter(|change| change.old != change.new)
+    }
+}
+
+// Every register that changed between two snapshots, sorted by address
+pub struct SnapshotDiff<MAP> {
+    pub changes: Vec<RegisterChange<MAP>, MAX_MAP_REGS>,
+}
+
+impl<MAP> SnapshotDiff<MAP>
+where
+    MAP: FieldMapProvider,
+{
+    pub fn new() -> Self {
+        Self { changes: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl<MAP> Default for SnapshotDiff<MAP>
+where
+    MAP: FieldMapProvider,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<MAP> fmt::Display for SnapshotDiff<MAP>
+where
+    MAP: FieldMapProvider,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // One line per changed register, followed by each changed field
+        for change in self.changes.iter() {
+            writeln!(f, "0x{:02X}: 0x{:02X} -> 0x{:02X}", change.reg, change.old, change.new)?;
+            for field_change in change.field_changes() {
+                writeln!(f, "    {}: {} -> {}", field_change.name, field_change.old, field_change.new)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I2C, MAP, DELAY> Chip<I2C, MAP, DELAY>
+where
+    I2C: RegisterInterface,
+    MAP: FieldMapProvider,
+    DELAY: DelayNs,
+{
+    pub fn dump(&mut self) -> Result<RegisterSnapshot<MAP>, I2CError<I2C>> {
+        // Read every register referenced by the field map
+        // Contiguous registers are coalesced into burst reads
+        let old_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+        let snapshot = self.read_snapshot();
+        log::set_max_level(old_level);
+
+        snapshot
+    }
+
+    pub fn restore(&mut self, snapshot: &RegisterSnapshot<MAP>) -> Result<(), I2CError<I2C>> {
+        // Write back every register in the snapshot that is fully writable
+        let old_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+        let result = snapshot.regs.iter()
+            .filter(|reg_val| MAP::reg_writable(reg_val.reg))
+            .try_for_each(|reg_val| self.write_reg(reg_val.reg, reg_val.value));
+        log::set_max_level(old_level);
+
+        result
+    }
+
+    pub fn check_drift(&mut self, expected: &RegisterSnapshot<MAP>) -> Result<SnapshotDiff<MAP>, I2CError<I2C>> {
+        // Compare the live configuration registers against an expected snapshot
+        // Read-only registers (measurement data, calibration) and volatile fields (e.g. mode) are not reported
+        let live = self.dump()?;
+        let mut diff = expected.diff(&live);
+        diff.changes.retain(|change| {
+            MAP::reg_writable(change.reg) && (change.old ^ change.new) & MAP::reg_verify_mask(change.reg) != 0
+        });
+
+        for change in diff.changes.iter() {
+            warn!("Register Drift: 0x{:.02X}, expected 0x{:.02X}, read 0x{:.02X}", change.reg, change.old, change.new);
+            for field_change in change.field_changes().filter(|field_change| !field_change.field.volatile) {
+                warn!("Field Drift: {}, expected {}, read {}", field_change.name, field_change.old, field_change.new);
+            }
+        }
+
+        Ok(diff)
+    }
+
+    pub fn correct_drift(&mut self, expected: &RegisterSnapshot<MAP>) -> Result<SnapshotDiff<MAP>, I2CError<I2C>> {
+        // Check for drift and write the expected bits back to every drifted register
+        // Volatile fields keep the value just read, so e.g. a forced measurement is not restarted
+        let diff = self.check_drift(expected)?;
+
+        let old_level = log::max_level();
+        log::set_max_level(log::LevelFilter::Off);
+        let result = diff.changes.iter().try_for_each(|change| {
+            let mask = MAP::reg_verify_mask(change.reg);
+            self.write_reg(change.reg, (change.old & mask) | (change.new & !mask))
+        });
+        log::set_max_level(old_level);
+        result?;
+
+        Ok(diff)
+    }
+
+    fn read_snapshot(&mut self) -> Result<RegisterSnapshot<MAP>, I2CError<I2C>> {
+        let mut snapshot = RegisterSnapshot::new();
+        let regs = MAP::regs().ok_or(I2CError::TooManyRegisters)?;
+        self.read_spans(&regs, |reg, value| {
+            // Capacity matches MAX_MAP_REGS so this cannot overflow
+            let _ = snapshot.regs.push(RegValue { reg, value });
+        })?;
+
+        Ok(snapshot)
+    }
+
+    pub(crate) fn read_spans(&mut self, regs: &[u16], mut store: impl FnMut(u16, u8)) -> Result<(), I2CError<I2C>> {
+        // Read a sorted, deduplicated list of registers, coalescing contiguous runs into burst reads
+        let mut span_start = 0;
+        while span_start < regs.len() {
+            // Grow the span while the next register is adjacent
+            let mut span_end = span_start + 1;
+            while span_end < regs.len()
+                && span_end - span_start < MAX_BURST_LEN
+                && regs[span_end] == self.reg_addr.offset(regs[span_end - 1], 1)
+            {
+                span_end += 1;
+            }
+
+            let mut buf = [0u8; MAX_BURST_LEN];
+            let span_vals = &mut buf[..span_end - span_start];
+            self.read_regs(regs[span_start], span_vals)?;
+
+            for (reg, value) in regs[span_start..span_end].iter().zip(span_vals.iter()) {
+                store(*reg, *value);
+            }
+
+            span_start = span_end;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phf::Map;
+    use phf_macros::phf_map;
+
+    struct TestMap;
+
+    static TEST_MAP: Map<&'static str, Field> = phf_map! {
+        "osrs_t" => Field { reg: 0x74, offset: 5, bits: 3, writable: true, volatile: false },
+        "mode" => Field { reg: 0x74, offset: 0, bits: 2, writable: true, volatile: false },
+        "filter" => Field { reg: 0x75, offset: 2, bits: 3, writable: true, volatile: false },
+    };
+
+    impl FieldMapProvider for TestMap {
+        fn map() -> &'static Map<&'static str, Field> {
+            &TEST_MAP
+        }
+    }
+
+    fn snapshot(regs: &[(u16, u8)]) -> RegisterSnapshot<TestMap> {
+        let mut snapshot = RegisterSnapshot::new();
+        for (reg, value) in regs.iter() {
+            let _ = snapshot.regs.push(RegValue { reg: *reg, value: *value });
+        }
+        snapshot
+    }
+
+    #[test]
+    fn diff_of_equal_snapshots_is_empty() {
+        let old = snapshot(&[(0x74, 0xb4), (0x75, 0x08)]);
+        assert!(old.diff(&old.clone()).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_changed_registers_and_fields() {
+        let old = snapshot(&[(0x72, 0x05), (0x74, 0b1010_0000), (0x75, 0x08)]);
+        let new = snapshot(&[(0x72, 0x05), (0x74, 0b1010_0001), (0x75, 0x08)]);
+        let diff = old.diff(&new);
+
+        assert_eq!(diff.changes.len(), 1);
+        let change = &diff.changes[0];
+        assert_eq!((change.reg, change.old, change.new), (0x74, 0b1010_0000, 0b1010_0001));
+
+        // osrs_t is unchanged, only mode is reported
+        let mut fields = change.field_changes();
+        let field = fields.next().unwrap();
+        assert_eq!((field.name, field.old, field.new), ("mode", 0b00, 0b01));
+        assert!(fields.next().is_none());
+    }
+
+    #[test]
+    fn diff_ignores_registers_missing_from_either_snapshot() {
+        let old = snapshot(&[(0x74, 0x00), (0x75, 0x00)]);
+        let new = snapshot(&[(0x75, 0x00), (0x76, 0xff)]);
+        assert!(old.diff(&new).is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+// telemetry.rs
+// Binary telemetry frames on a byte link, far more compact than the text log
+use rtt_target::UpChannel;
+
+use crate::sample::{Sample, SampleSink};
+
+pub use telemetry_frame::{
+    cobs_decode, cobs_encode, crc16, DecodeError, Frame, FrameDecoder, Payload,
+    MAX_FRAME_LEN, MAX_FRAME_REGS, VERSION,
+};
+
+// Byte link that takes whole encoded frames
+pub trait FrameWriter {
+    // false if the frame was not written in full
+    fn write_frame(&mut self, frame: &[u8]) -> bool;
+}
+
+impl FrameWriter for UpChannel {
+    fn write_frame(&mut self, frame: &[u8]) -> bool {
+        self.write(frame) == frame.len()
+    }
+}
+
+pub struct FrameSink<W> {
+    pub writer: W,
+    pub dropped: u32,
+}
+
+impl<W: FrameWriter> FrameSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer, dropped: 0 }
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) {
+        let mut buf = [0u8; MAX_FRAME_LEN];
+        let len = frame.encode(&mut buf);
+        if !self.writer.write_frame(&buf[..len]) {
+            self.dropped += 1;
+        }
+    }
+
+    pub fn write_registers(&mut self, sensor: u8, timestamp_us: u64, start: u16, values: &[u8]) {
+        // Register dump, split over as many frames as needed
+        for (idx, chunk) in values.chunks(MAX_FRAME_REGS).enumerate() {
+            let reg = start.wrapping_add((idx * MAX_FRAME_REGS) as u16);
+            if let Some(frame) = Frame::registers(sensor, timestamp_us, reg, chunk) {
+                self.write_frame(&frame);
+            }
+        }
+    }
+}
+
+impl<W: FrameWriter> SampleSink for FrameSink<W> {
+    fn write(&mut self, sample: &Sample) {
+        self.write_frame(&Frame::from(*sample));
+    }
+}
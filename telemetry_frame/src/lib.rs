@@ -0,0 +1,283 @@
+#![no_std]
+// Compact binary telemetry frames, shared by the firmware encoder and the host decoder
+//
+// Frame before framing, multi-byte fields little endian:
+//   version u8 | sensor u8 | timestamp_us u64 | payload type u8 | payload | crc16 u16
+// The whole frame is COBS encoded and terminated by a single 0x00 on the wire
+// so a receiver can resync on the next delimiter after a dropped byte
+use core::fmt;
+
+pub mod sample;
+
+pub use sample::{Channel, Sample, Status, Unit, SAMPLE_LEN};
+
+pub const VERSION: u8 = 1;
+pub const HEADER_LEN: usize = 11;
+pub const CRC_LEN: usize = 2;
+pub const MAX_FRAME_REGS: usize = 32;  // Register bytes per Registers payload
+pub const MAX_PAYLOAD_LEN: usize = 3 + MAX_FRAME_REGS;
+pub const MAX_RAW_LEN: usize = HEADER_LEN + MAX_PAYLOAD_LEN + CRC_LEN;
+pub const MAX_FRAME_LEN: usize = MAX_RAW_LEN + MAX_RAW_LEN / 254 + 2;  // COBS overhead plus delimiter
+
+const PAYLOAD_SAMPLE: u8 = 0x01;
+const PAYLOAD_REGISTERS: u8 = 0x02;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Payload {
+    // channel u8 | unit u8 | status u8 | value i32
+    Sample { channel: Channel, unit: Unit, status: Status, value: i32 },
+    // start u16 | len u8 | data, raw register contents replacing the "Read Register" log lines
+    Registers { start: u16, len: u8, data: [u8; MAX_FRAME_REGS] },
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    pub sensor: u8,
+    pub timestamp_us: u64,
+    pub payload: Payload,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    Cobs,
+    TooShort { len: usize },
+    TooLong,
+    Crc { expected: u16, read: u16 },
+    Version(u8),
+    PayloadType(u8),
+    InvalidPayload,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cobs => write!(f, "Invalid COBS encoding"),
+            Self::TooShort { len } => write!(f, "Frame too short: {} bytes", len),
+            Self::TooLong => write!(f, "Frame longer than {} bytes", MAX_FRAME_LEN),
+            Self::Crc { expected, read } => write!(f, "CRC mismatch: expected 0x{:04x}, read 0x{:04x}", expected, read),
+            Self::Version(version) => write!(f, "Unsupported frame version: {}", version),
+            Self::PayloadType(kind) => write!(f, "Unknown payload type: 0x{:02x}", kind),
+            Self::InvalidPayload => write!(f, "Invalid payload"),
+        }
+    }
+}
+
+pub fn crc16(bytes: &[u8]) -> u16 {
+    // CRC-16/CCITT-FALSE, poly 0x1021, init 0xffff
+    let mut crc: u16 = 0xffff;
+    for &byte in bytes {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 { (crc << 1) ^ 0x1021 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+pub fn cobs_encode(src: &[u8], dst: &mut [u8]) -> usize {
+    // dst must hold src.len() + src.len() / 254 + 1 bytes, no delimiter is added
+    let mut code_idx = 0;
+    let mut out = 1;
+    let mut code: u8 = 1;
+    for &byte in src {
+        if byte != 0 {
+            dst[out] = byte;
+            out += 1;
+            code += 1;
+        }
+        if byte == 0 || code == 0xff {
+            dst[code_idx] = code;
+            code_idx = out;
+            out += 1;
+            code = 1;
+        }
+    }
+    dst[code_idx] = code;
+    out
+}
+
+pub fn cobs_decode(src: &[u8], dst: &mut [u8]) -> Result<usize, DecodeError> {
+    // src without the delimiter
+    let mut i = 0;
+    let mut out = 0;
+    while i < src.len() {
+        let code = src[i] as usize;
+        if code == 0 || i + code > src.len() {
+            return Err(DecodeError::Cobs);
+        }
+        for &byte in &src[i + 1..i + code] {
+            if byte == 0 {
+                return Err(DecodeError::Cobs);
+            }
+            *dst.get_mut(out).ok_or(DecodeError::TooLong)? = byte;
+            out += 1;
+        }
+        i += code;
+
+        // A block shorter than 254 bytes stands for a zero, except at the end
+        if code < 0xff && i < src.len() {
+            *dst.get_mut(out).ok_or(DecodeError::TooLong)? = 0;
+            out += 1;
+        }
+    }
+    Ok(out)
+}
+
+impl Frame {
+    pub fn registers(sensor: u8, timestamp_us: u64, start: u16, bytes: &[u8]) -> Option<Self> {
+        // None if the bytes don't fit one frame, split them at MAX_FRAME_REGS
+        if bytes.len() > MAX_FRAME_REGS {
+            return None;
+        }
+        let mut data = [0u8; MAX_FRAME_REGS];
+        data[..bytes.len()].copy_from_slice(bytes);
+        Some(Self { sensor, timestamp_us, payload: Payload::Registers { start, len: bytes.len() as u8, data } })
+    }
+
+    pub fn sample(&self) -> Option<Sample> {
+        match self.payload {
+            Payload::Sample { channel, unit, status, value } => {
+                Some(Sample { sensor: self.sensor, timestamp_us: self.timestamp_us, channel, value, unit, status })
+            }
+            Payload::Registers { .. } => None,
+        }
+    }
+
+    pub fn to_raw(&self, raw: &mut [u8; MAX_RAW_LEN]) -> usize {
+        // Unframed bytes including the CRC
+        raw[0] = VERSION;
+        raw[1] = self.sensor;
+        raw[2..10].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        let mut len = HEADER_LEN;
+        match self.payload {
+            Payload::Sample { channel, unit, status, value } => {
+                raw[10] = PAYLOAD_SAMPLE;
+                raw[11] = channel as u8;
+                raw[12] = unit as u8;
+                raw[13] = status as u8;
+                raw[14..18].copy_from_slice(&value.to_le_bytes());
+                len += 7;
+            }
+            Payload::Registers { start, len: reg_len, data } => {
+                raw[10] = PAYLOAD_REGISTERS;
+                raw[11..13].copy_from_slice(&start.to_le_bytes());
+                raw[13] = reg_len;
+                raw[14..14 + reg_len as usize].copy_from_slice(&data[..reg_len as usize]);
+                len += 3 + reg_len as usize;
+            }
+        }
+        let crc = crc16(&raw[..len]);
+        raw[len..len + CRC_LEN].copy_from_slice(&crc.to_le_bytes());
+        len + CRC_LEN
+    }
+
+    pub fn from_raw(raw: &[u8]) -> Result<Self, DecodeError> {
+        if raw.len() < HEADER_LEN + CRC_LEN {
+            return Err(DecodeError::TooShort { len: raw.len() });
+        }
+        let (body, crc_bytes) = raw.split_at(raw.len() - CRC_LEN);
+        let read = u16::from_le_bytes([crc_bytes[0], crc_bytes[1]]);
+        let expected = crc16(body);
+        if read != expected {
+            return Err(DecodeError::Crc { expected, read });
+        }
+        if body[0] != VERSION {
+            return Err(DecodeError::Version(body[0]));
+        }
+
+        let sensor = body[1];
+        let timestamp_us = u64::from_le_bytes(body[2..10].try_into().map_err(|_| DecodeError::InvalidPayload)?);
+        let payload = &body[HEADER_LEN..];
+        let payload = match body[10] {
+            PAYLOAD_SAMPLE => {
+                if payload.len() != 7 {
+                    return Err(DecodeError::InvalidPayload);
+                }
+                Payload::Sample {
+                    channel: Channel::from_u8(payload[0]).ok_or(DecodeError::InvalidPayload)?,
+                    unit: Unit::from_u8(payload[1]).ok_or(DecodeError::InvalidPayload)?,
+                    status: Status::from_u8(payload[2]).ok_or(DecodeError::InvalidPayload)?,
+                    value: i32::from_le_bytes([payload[3], payload[4], payload[5], payload[6]]),
+                }
+            }
+            PAYLOAD_REGISTERS => {
+                let reg_len = *payload.get(2).ok_or(DecodeError::InvalidPayload)? as usize;
+                if reg_len > MAX_FRAME_REGS || payload.len() != 3 + reg_len {
+                    return Err(DecodeError::InvalidPayload);
+                }
+                let mut data = [0u8; MAX_FRAME_REGS];
+                data[..reg_len].copy_from_slice(&payload[3..]);
+                Payload::Registers { start: u16::from_le_bytes([payload[0], payload[1]]), len: reg_len as u8, data }
+            }
+            kind => return Err(DecodeError::PayloadType(kind)),
+        };
+
+        Ok(Self { sensor, timestamp_us, payload })
+    }
+
+    pub fn encode(&self, out: &mut [u8; MAX_FRAME_LEN]) -> usize {
+        // Wire bytes, COBS encoded and delimited
+        let mut raw = [0u8; MAX_RAW_LEN];
+        let raw_len = self.to_raw(&mut raw);
+        let len = cobs_encode(&raw[..raw_len], out);
+        out[len] = 0;
+        len + 1
+    }
+
+    pub fn decode(encoded: &[u8]) -> Result<Self, DecodeError> {
+        // One COBS encoded frame, with or without its delimiter
+        let encoded = encoded.strip_suffix(&[0]).unwrap_or(encoded);
+        let mut raw = [0u8; MAX_RAW_LEN];
+        let len = cobs_decode(encoded, &mut raw)?;
+        Self::from_raw(&raw[..len])
+    }
+}
+
+impl From<Sample> for Frame {
+    fn from(sample: Sample) -> Self {
+        let Sample { sensor, timestamp_us, channel, value, unit, status } = sample;
+        Self { sensor, timestamp_us, payload: Payload::Sample { channel, unit, status, value } }
+    }
+}
+
+// Splits a byte stream on delimiters and decodes each frame
+pub struct FrameDecoder {
+    buf: [u8; MAX_FRAME_LEN],
+    len: usize,
+    overflow: bool,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        Self { buf: [0; MAX_FRAME_LEN], len: 0, overflow: false }
+    }
+
+    pub fn push(&mut self, byte: u8) -> Option<Result<Frame, DecodeError>> {
+        // Some once a delimiter ends a frame, empty frames between delimiters are skipped
+        if byte != 0 {
+            match self.buf.get_mut(self.len) {
+                Some(slot) => {
+                    *slot = byte;
+                    self.len += 1;
+                }
+                None => self.overflow = true,
+            }
+            return None;
+        }
+
+        let result = match (self.len, self.overflow) {
+            (0, false) => None,
+            (_, true) => Some(Err(DecodeError::TooLong)),
+            (len, false) => Some(Frame::decode(&self.buf[..len])),
+        };
+        self.len = 0;
+        self.overflow = false;
+        result
+    }
+}
+
+impl Default for FrameDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
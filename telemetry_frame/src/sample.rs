@@ -0,0 +1,140 @@
+// sample.rs
+// Typed measurement records
+use core::fmt;
+
+pub const SAMPLE_LEN: usize = 16;  // Size of a record in binary form
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Channel {
+    Temperature = 0,
+    Pressure = 1,
+    Humidity = 2,
+    GasResistance = 3,
+}
+
+// Units are fixed point, the scale is part of the unit
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Unit {
+    CentiDegC = 0,  // 0.01 °C
+    Pascal = 1,
+    MilliPercentRh = 2,  // 0.001 %RH
+    Ohm = 3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Status {
+    Ok = 0,
+    Unstable = 1,  // Value taken before the sensor settled, e.g. gas heater not stable
+    Error = 2,  // Read failed, value is meaningless
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sample {
+    pub sensor: u8,  // Caller assigned sensor ID
+    pub timestamp_us: u64,
+    pub channel: Channel,
+    pub value: i32,
+    pub unit: Unit,
+    pub status: Status,
+}
+
+impl Channel {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        [Self::Temperature, Self::Pressure, Self::Humidity, Self::GasResistance].into_iter().find(|c| *c as u8 == value)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Temperature => "temperature",
+            Self::Pressure => "pressure",
+            Self::Humidity => "humidity",
+            Self::GasResistance => "gas_resistance",
+        }
+    }
+}
+
+impl Unit {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        [Self::CentiDegC, Self::Pascal, Self::MilliPercentRh, Self::Ohm].into_iter().find(|u| *u as u8 == value)
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::CentiDegC => "°C",
+            Self::Pascal => "Pa",
+            Self::MilliPercentRh => "%RH",
+            Self::Ohm => "Ohm",
+        }
+    }
+
+    pub fn decimals(&self) -> u32 {
+        match self {
+            Self::CentiDegC => 2,
+            Self::MilliPercentRh => 3,
+            Self::Pascal | Self::Ohm => 0,
+        }
+    }
+}
+
+impl Status {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        [Self::Ok, Self::Unstable, Self::Error].into_iter().find(|s| *s as u8 == value)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Ok => "ok",
+            Self::Unstable => "unstable",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl Sample {
+    pub fn error(sensor: u8, timestamp_us: u64, channel: Channel, unit: Unit) -> Self {
+        Self { sensor, timestamp_us, channel, value: 0, unit, status: Status::Error }
+    }
+
+    pub fn to_bytes(&self) -> [u8; SAMPLE_LEN] {
+        // sensor, timestamp (LE), channel, value (LE), unit, status
+        let mut bytes = [0u8; SAMPLE_LEN];
+        bytes[0] = self.sensor;
+        bytes[1..9].copy_from_slice(&self.timestamp_us.to_le_bytes());
+        bytes[9] = self.channel as u8;
+        bytes[10..14].copy_from_slice(&self.value.to_le_bytes());
+        bytes[14] = self.unit as u8;
+        bytes[15] = self.status as u8;
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; SAMPLE_LEN]) -> Option<Self> {
+        Some(Self {
+            sensor: bytes[0],
+            timestamp_us: u64::from_le_bytes(bytes[1..9].try_into().ok()?),
+            channel: Channel::from_u8(bytes[9])?,
+            value: i32::from_le_bytes(bytes[10..14].try_into().ok()?),
+            unit: Unit::from_u8(bytes[14])?,
+            status: Status::from_u8(bytes[15])?,
+        })
+    }
+}
+
+impl fmt::Display for Sample {
+    // e.g. "1 @ 1000000 us: temperature 23.45 °C (ok)"
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} @ {} us: {} ", self.sensor, self.timestamp_us, self.channel.name())?;
+        let decimals = self.unit.decimals();
+        if decimals == 0 {
+            write!(f, "{}", self.value)?;
+        } else {
+            let scale = 10i32.pow(decimals);
+            let sign = if self.value < 0 { "-" } else { "" };
+            let abs = self.value.unsigned_abs();
+            write!(f, "{}{}.{:0width$}", sign, abs / scale as u32, abs % scale as u32, width = decimals as usize)?;
+        }
+        write!(f, " {} ({})", self.unit.symbol(), self.status.name())
+    }
+}
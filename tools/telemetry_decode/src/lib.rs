@@ -0,0 +1,70 @@
+// Host side decoding of captured telemetry streams into CSV or JSON
+use std::fmt::Write;
+
+use telemetry_frame::{DecodeError, Frame, FrameDecoder, Payload};
+
+pub const CSV_HEADER: &str = "sensor,timestamp_us,kind,channel,value,unit,status,start,data";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+}
+
+pub fn decode_stream(bytes: &[u8]) -> Vec<Result<Frame, DecodeError>> {
+    // Every frame in a capture, a capture cut mid-frame loses only that frame
+    let mut decoder = FrameDecoder::new();
+    bytes.iter().filter_map(|&byte| decoder.push(byte)).collect()
+}
+
+fn hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut out, byte| {
+        let _ = write!(out, "{:02x}", byte);
+        out
+    })
+}
+
+fn scaled(value: i32, decimals: u32) -> String {
+    // Fixed point value as a plain decimal number
+    if decimals == 0 {
+        return value.to_string();
+    }
+    let scale = 10u32.pow(decimals);
+    let sign = if value < 0 { "-" } else { "" };
+    let abs = value.unsigned_abs();
+    format!("{}{}.{:0width$}", sign, abs / scale, abs % scale, width = decimals as usize)
+}
+
+pub fn to_csv(frame: &Frame) -> String {
+    match frame.payload {
+        Payload::Sample { channel, unit, status, value } => format!(
+            "{},{},sample,{},{},{},{},,",
+            frame.sensor, frame.timestamp_us, channel.name(), scaled(value, unit.decimals()), unit.symbol(), status.name(),
+        ),
+        Payload::Registers { start, len, data } => format!(
+            "{},{},registers,,,,,0x{:04x},{}",
+            frame.sensor, frame.timestamp_us, start, hex(&data[..len as usize]),
+        ),
+    }
+}
+
+pub fn to_json(frame: &Frame) -> String {
+    // One object per line, unit symbols are plain UTF-8 so no escaping is needed
+    match frame.payload {
+        Payload::Sample { channel, unit, status, value } => format!(
+            r#"{{"sensor":{},"timestamp_us":{},"kind":"sample","channel":"{}","value":{},"unit":"{}","status":"{}"}}"#,
+            frame.sensor, frame.timestamp_us, channel.name(), scaled(value, unit.decimals()), unit.symbol(), status.name(),
+        ),
+        Payload::Registers { start, len, data } => format!(
+            r#"{{"sensor":{},"timestamp_us":{},"kind":"registers","start":{},"data":"{}"}}"#,
+            frame.sensor, frame.timestamp_us, start, hex(&data[..len as usize]),
+        ),
+    }
+}
+
+pub fn render(frame: &Frame, format: Format) -> String {
+    match format {
+        Format::Csv => to_csv(frame),
+        Format::Json => to_json(frame),
+    }
+}
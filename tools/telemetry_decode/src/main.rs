@@ -0,0 +1,43 @@
+// telemetry_decode
+// Turns a captured telemetry stream into CSV or JSON lines
+//
+// Usage: telemetry_decode [--json] [capture.bin]
+// Reads stdin when no file is given, bad frames are reported on stderr and skipped
+use std::io::{self, Read};
+use std::process::ExitCode;
+
+use telemetry_decode::{decode_stream, render, Format, CSV_HEADER};
+
+fn main() -> ExitCode {
+    let mut format = Format::Csv;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => format = Format::Json,
+            "--csv" => format = Format::Csv,
+            _ => path = Some(arg),
+        }
+    }
+
+    let mut bytes = Vec::new();
+    let read = match &path {
+        Some(path) => std::fs::File::open(path).and_then(|mut file| file.read_to_end(&mut bytes)),
+        None => io::stdin().read_to_end(&mut bytes),
+    };
+    if let Err(err) = read {
+        eprintln!("Failed to read capture: {}", err);
+        return ExitCode::FAILURE;
+    }
+
+    if format == Format::Csv {
+        println!("{}", CSV_HEADER);
+    }
+    for result in decode_stream(&bytes) {
+        match result {
+            Ok(frame) => println!("{}", render(&frame, format)),
+            Err(err) => eprintln!("Bad frame: {}", err),
+        }
+    }
+
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,143 @@
+// Encode with the firmware encoder, decode with the host decoder
+use telemetry_decode::{decode_stream, to_csv, to_json};
+use telemetry_frame::{
+    cobs_decode, cobs_encode, crc16, Channel, DecodeError, Frame, FrameDecoder, Sample, Status, Unit, MAX_FRAME_LEN,
+    MAX_FRAME_REGS, SAMPLE_LEN,
+};
+
+fn sample(channel: Channel, value: i32, unit: Unit, status: Status) -> Sample {
+    Sample { sensor: 0x76, timestamp_us: 1_234_567_890_123, channel, value, unit, status }
+}
+
+fn samples() -> Vec<Sample> {
+    vec![
+        sample(Channel::Temperature, 2345, Unit::CentiDegC, Status::Ok),
+        sample(Channel::Temperature, -512, Unit::CentiDegC, Status::Ok),
+        sample(Channel::Pressure, 101_325, Unit::Pascal, Status::Ok),
+        sample(Channel::Humidity, 45_678, Unit::MilliPercentRh, Status::Ok),
+        sample(Channel::GasResistance, 0, Unit::Ohm, Status::Unstable),
+        sample(Channel::GasResistance, 0, Unit::Ohm, Status::Error),
+        Sample { sensor: 0, timestamp_us: 0, channel: Channel::Pressure, value: 0, unit: Unit::Pascal, status: Status::Ok },
+        Sample { sensor: 0xff, timestamp_us: u64::MAX, channel: Channel::Humidity, value: i32::MIN, unit: Unit::MilliPercentRh, status: Status::Ok },
+    ]
+}
+
+fn encode(frame: &Frame) -> Vec<u8> {
+    let mut buf = [0u8; MAX_FRAME_LEN];
+    let len = frame.encode(&mut buf);
+    buf[..len].to_vec()
+}
+
+#[test]
+fn crc16_check_value() {
+    // CRC-16/CCITT-FALSE check value
+    assert_eq!(crc16(b"123456789"), 0x29b1);
+}
+
+#[test]
+fn cobs_round_trip() {
+    let inputs: Vec<Vec<u8>> = vec![
+        vec![],
+        vec![0],
+        vec![0, 0],
+        vec![1, 2, 0, 3],
+        (1..=254).collect(),
+        (1..=255).collect(),
+        (0..=255).cycle().take(600).collect(),
+    ];
+    for input in inputs {
+        let mut encoded = vec![0u8; input.len() + input.len() / 254 + 1];
+        let len = cobs_encode(&input, &mut encoded);
+        assert!(!encoded[..len].contains(&0), "encoded {:?} holds a zero", input);
+
+        let mut decoded = vec![0u8; input.len()];
+        let decoded_len = cobs_decode(&encoded[..len], &mut decoded).unwrap();
+        assert_eq!(&decoded[..decoded_len], &input[..]);
+    }
+}
+
+#[test]
+fn sample_frames_round_trip() {
+    for sample in samples() {
+        let encoded = encode(&Frame::from(sample));
+        assert_eq!(encoded.last(), Some(&0));
+        assert_eq!(encoded.iter().filter(|&&byte| byte == 0).count(), 1);
+
+        let frame = Frame::decode(&encoded).unwrap();
+        assert_eq!(frame.sample(), Some(sample));
+    }
+}
+
+#[test]
+fn sample_bytes_round_trip() {
+    for sample in samples() {
+        let bytes: [u8; SAMPLE_LEN] = sample.to_bytes();
+        assert_eq!(Sample::from_bytes(&bytes), Some(sample));
+    }
+}
+
+#[test]
+fn register_frames_round_trip() {
+    let regs: Vec<u8> = (0..MAX_FRAME_REGS as u8).map(|reg| reg.wrapping_mul(37)).collect();
+    for len in [0, 1, 15, MAX_FRAME_REGS] {
+        let frame = Frame::registers(1, 42, 0x1d, &regs[..len]).unwrap();
+        assert_eq!(Frame::decode(&encode(&frame)).unwrap(), frame);
+    }
+    assert_eq!(Frame::registers(1, 42, 0x1d, &[0; MAX_FRAME_REGS + 1]), None);
+}
+
+#[test]
+fn stream_round_trip() {
+    let frames: Vec<Frame> = samples().into_iter().map(Frame::from).collect();
+    let stream: Vec<u8> = frames.iter().flat_map(encode).collect();
+
+    let decoded: Vec<Frame> = decode_stream(&stream).into_iter().map(Result::unwrap).collect();
+    assert_eq!(decoded, frames);
+}
+
+#[test]
+fn stream_resyncs_after_corruption() {
+    let frames: Vec<Frame> = samples().into_iter().map(Frame::from).collect();
+    let mut stream = Vec::new();
+    // Capture started mid-frame
+    stream.extend_from_slice(&encode(&frames[0])[5..]);
+    // Flipped bit
+    let mut corrupt = encode(&frames[1]);
+    corrupt[4] ^= 0x10;
+    stream.extend_from_slice(&corrupt);
+    stream.extend_from_slice(&encode(&frames[2]));
+
+    let results = decode_stream(&stream);
+    assert_eq!(results.len(), 3);
+    assert!(results[0].is_err());
+    assert!(matches!(results[1], Err(DecodeError::Crc { .. })));
+    assert_eq!(results[2], Ok(frames[2]));
+}
+
+#[test]
+fn decoder_rejects_oversized_frames() {
+    let mut decoder = FrameDecoder::new();
+    for _ in 0..MAX_FRAME_LEN + 10 {
+        assert_eq!(decoder.push(0x01), None);
+    }
+    assert_eq!(decoder.push(0), Some(Err(DecodeError::TooLong)));
+
+    // Back in sync for the next frame
+    let frame = Frame::from(samples()[0]);
+    let results: Vec<_> = encode(&frame).into_iter().filter_map(|byte| decoder.push(byte)).collect();
+    assert_eq!(results, vec![Ok(frame)]);
+}
+
+#[test]
+fn csv_and_json_output() {
+    let frame = Frame::from(sample(Channel::Temperature, -512, Unit::CentiDegC, Status::Ok));
+    assert_eq!(to_csv(&frame), "118,1234567890123,sample,temperature,-5.12,°C,ok,,");
+    assert_eq!(
+        to_json(&frame),
+        r#"{"sensor":118,"timestamp_us":1234567890123,"kind":"sample","channel":"temperature","value":-5.12,"unit":"°C","status":"ok"}"#,
+    );
+
+    let frame = Frame::registers(118, 7, 0x74, &[0xe3, 0x00]).unwrap();
+    assert_eq!(to_csv(&frame), "118,7,registers,,,,,0x0074,e300");
+    assert_eq!(to_json(&frame), r#"{"sensor":118,"timestamp_us":7,"kind":"registers","start":116,"data":"e300"}"#);
+}